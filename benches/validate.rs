@@ -0,0 +1,142 @@
+//! Benchmarks of `id_token` validation with cached certs, with and without certs being refreshed concurrently.
+//!
+//! Run with `cargo bench`. No network access is needed: certs come from a `StaticCertSource`.
+//!
+//! `AsyncClient` is not available with feature `wasm`, so there is nothing to measure then.
+
+#[cfg(not(feature = "wasm"))]
+mod validate {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+    use base64::Engine;
+    use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+    use criterion::{criterion_group, Criterion};
+    use google_oauth::{AsyncClient, Cert, Certs, StaticCertSource};
+    use rsa::RsaPrivateKey;
+    use rsa::pkcs1v15::SigningKey;
+    use rsa::sha2::Sha256;
+    use rsa::signature::{SignatureEncoding, Signer};
+    use rsa::traits::PublicKeyParts;
+
+    const CLIENT_ID: &str = "bench-client-id";
+    const KID: &str = "bench-kid";
+    const TASKS: u64 = 8;
+
+    fn certs(key: &RsaPrivateKey) -> Certs {
+        let pk = key.to_public_key();
+
+        Certs::new(vec![Cert {
+            kid: KID.to_string(),
+            alg: "RS256".to_string(),
+            kty: "RSA".to_string(),
+            e: BASE64_URL_SAFE_NO_PAD.encode(pk.e().to_bytes_be()),
+            n: BASE64_URL_SAFE_NO_PAD.encode(pk.n().to_bytes_be()),
+            crv: None,
+            x: None,
+            y: None,
+            not_after: None,
+        }])
+    }
+
+    fn id_token(key: &RsaPrivateKey) -> String {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+        let header = serde_json::json!({ "alg": "RS256", "kid": KID, "typ": "JWT" });
+        let payload = serde_json::json!({
+            "aud": CLIENT_ID,
+            "iss": "https://accounts.google.com",
+            "sub": "bench-user",
+            "iat": now,
+            "exp": now + 3600,
+        });
+
+        let msg = format!(
+            "{}.{}",
+            BASE64_URL_SAFE_NO_PAD.encode(header.to_string()),
+            BASE64_URL_SAFE_NO_PAD.encode(payload.to_string()),
+        );
+        let sig = SigningKey::<Sha256>::new(key.clone()).sign(msg.as_bytes());
+
+        format!("{}.{}", msg, BASE64_URL_SAFE_NO_PAD.encode(sig.to_bytes()))
+    }
+
+    /// Validate `iters` tokens with `TASKS` concurrent tasks, optionally refreshing certs in a loop meanwhile.
+    async fn validate_concurrently(client: &AsyncClient, token: &Arc<String>, iters: u64, refreshing: bool) -> Duration {
+        let done = Arc::new(AtomicBool::new(false));
+
+        let refresher = refreshing.then(|| {
+            let client = client.clone();
+            let done = done.clone();
+
+            tokio::spawn(async move {
+                while !done.load(Ordering::Relaxed) {
+                    client.refresh_certs().await.unwrap();
+                    tokio::task::yield_now().await;
+                }
+            })
+        });
+
+        let start = Instant::now();
+
+        let tasks: Vec<_> = (0..TASKS)
+            .map(|_| {
+                let client = client.clone();
+                let token = token.clone();
+
+                tokio::spawn(async move {
+                    for _ in 0..iters.div_ceil(TASKS) {
+                        client.validate_id_token(token.as_str()).await.unwrap();
+                    }
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        let elapsed = start.elapsed();
+
+        done.store(true, Ordering::Relaxed);
+        if let Some(refresher) = refresher {
+            refresher.await.unwrap();
+        }
+
+        elapsed
+    }
+
+    fn bench_validate(c: &mut Criterion) {
+        let key = RsaPrivateKey::new(&mut rsa::rand_core::OsRng, 2048).unwrap();
+        let client = AsyncClient::new(CLIENT_ID).cert_source(StaticCertSource::new(certs(&key)));
+        let token = Arc::new(id_token(&key));
+
+        let rt = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(TASKS as usize)
+            .build()
+            .unwrap();
+
+        // fill the cache before measuring.
+        rt.block_on(client.validate_id_token(token.as_str())).unwrap();
+
+        c.bench_function("validate_id_token", |b| {
+            b.iter(|| rt.block_on(client.validate_id_token(token.as_str())).unwrap())
+        });
+
+        c.bench_function("validate_id_token/concurrent", |b| {
+            b.iter_custom(|iters| rt.block_on(validate_concurrently(&client, &token, iters, false)))
+        });
+
+        c.bench_function("validate_id_token/concurrent_with_refresh", |b| {
+            b.iter_custom(|iters| rt.block_on(validate_concurrently(&client, &token, iters, true)))
+        });
+    }
+
+    criterion_group!(benches, bench_validate);
+}
+
+#[cfg(not(feature = "wasm"))]
+criterion::criterion_main!(validate::benches);
+
+#[cfg(feature = "wasm")]
+fn main() {}
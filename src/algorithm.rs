@@ -0,0 +1,79 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::HashAlgorithmUnimplementedError;
+
+/// `Algorithm` is the signing algorithm of a JWT, which is listed in the `alg` field of the JWT header.
+///
+/// see https://www.rfc-editor.org/rfc/rfc7518#section-3.1 for more info.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum Algorithm {
+    /// RSASSA-PKCS1-v1_5 using SHA-256
+    RS256,
+    /// RSASSA-PKCS1-v1_5 using SHA-384
+    RS384,
+    /// RSASSA-PKCS1-v1_5 using SHA-512
+    RS512,
+    /// RSASSA-PSS using SHA-256 and MGF1 with SHA-256
+    PS256,
+    /// RSASSA-PSS using SHA-384 and MGF1 with SHA-384
+    PS384,
+    /// RSASSA-PSS using SHA-512 and MGF1 with SHA-512
+    PS512,
+    /// ECDSA using P-256 and SHA-256
+    ES256,
+}
+
+impl Algorithm {
+    /// All supported algorithms.
+    pub const ALL: [Algorithm; 7] = [
+        Self::RS256, Self::RS384, Self::RS512,
+        Self::PS256, Self::PS384, Self::PS512,
+        Self::ES256,
+    ];
+
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::RS256 => "RS256",
+            Self::RS384 => "RS384",
+            Self::RS512 => "RS512",
+            Self::PS256 => "PS256",
+            Self::PS384 => "PS384",
+            Self::PS512 => "PS512",
+            Self::ES256 => "ES256",
+        }
+    }
+
+    /// The `kty` of the JWK which can be used to verify this algorithm.
+    #[inline]
+    pub fn key_type(&self) -> &'static str {
+        match self {
+            Self::ES256 => "EC",
+            _ => "RSA",
+        }
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = HashAlgorithmUnimplementedError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "RS256" => Ok(Self::RS256),
+            "RS384" => Ok(Self::RS384),
+            "RS512" => Ok(Self::RS512),
+            "PS256" => Ok(Self::PS256),
+            "PS384" => Ok(Self::PS384),
+            "PS512" => Ok(Self::PS512),
+            "ES256" => Ok(Self::ES256),
+            a => Err(HashAlgorithmUnimplementedError::new(a)),
+        }
+    }
+}
+
+impl Display for Algorithm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
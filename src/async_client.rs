@@ -1,26 +1,42 @@
 #![allow(non_upper_case_globals)]
 
-use std::ops::Add;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 use lazy_static::lazy_static;
 use log::debug;
-use async_lock::RwLock;
-use crate::{DEFAULT_TIMEOUT, GOOGLE_OAUTH_V3_USER_INFO_API, GOOGLE_SA_CERTS_URL, GoogleAccessTokenPayload, GooglePayload, MyResult, utils};
-use crate::certs::{Cert, Certs};
+use arc_swap::ArcSwap;
+use async_lock::Mutex as AsyncMutex;
+use crate::{BACKGROUND_REFRESH_GRACE, DEFAULT_FORCED_REFRESH_INTERVAL, DEFAULT_TIMEOUT, Error, GOOGLE_OAUTH_V3_USER_INFO_API, GOOGLE_SA_CERTS_URL, GoogleAccessTokenPayload, CacheOptions, GooglePayload, IDTokenClaims, IDTokenClientIDNotFoundError, ServiceIdTokenPayload, MyResult, ValidationOptions};
+use crate::certs::Certs;
+use crate::cert_source::{AsyncCertSource, HttpCertSource};
 use crate::jwt_parser::JwtParser;
-use crate::validate::id_token;
+use crate::validate::{id_token, policy};
+use crate::validate::key::VerifyingKey;
+#[cfg(feature = "background-refresh")]
+use crate::utils::RefresherGuard;
 
 lazy_static! {
-    static ref ca: reqwest::Client = reqwest::Client::new();
+    pub(crate) static ref ca: reqwest::Client = reqwest::Client::new();
 }
 
 /// AsyncClient is an async client to do verification.
 #[derive(Debug, Clone)]
 pub struct AsyncClient {
-    client_ids: Arc<RwLock<Vec<String>>>,
+    client_ids: Arc<ArcSwap<Vec<String>>>,
     timeout: Duration,
-    cached_certs: Arc<RwLock<Certs>>,
+    options: ValidationOptions,
+    certs_url: String,
+    userinfo_url: String,
+    cert_source: Option<Arc<dyn AsyncCertSource>>,
+    cached_certs: Arc<ArcSwap<Certs>>,
+    refresh_lock: Arc<AsyncMutex<()>>,
+    background_refreshers: Arc<AtomicUsize>,
+    forced_refresh_interval: Option<Duration>,
+    last_forced_refresh: Arc<Mutex<Option<Instant>>>,
+    persistent_cache: Option<PathBuf>,
+    cache_options: CacheOptions,
 }
 
 impl AsyncClient {
@@ -37,7 +53,7 @@ impl AsyncClient {
             V: AsRef<str>,
     {
         Self {
-            client_ids: Arc::new(RwLock::new(
+            client_ids: Arc::new(ArcSwap::from_pointee(
                 client_ids
                     .as_ref()
                     .iter()
@@ -47,7 +63,17 @@ impl AsyncClient {
                     .collect()
             )),
             timeout: Duration::from_secs(DEFAULT_TIMEOUT),
+            options: ValidationOptions::default(),
+            certs_url: GOOGLE_SA_CERTS_URL.to_string(),
+            userinfo_url: GOOGLE_OAUTH_V3_USER_INFO_API.to_string(),
+            cert_source: None,
             cached_certs: Arc::default(),
+            refresh_lock: Arc::default(),
+            background_refreshers: Arc::default(),
+            forced_refresh_interval: Some(Duration::from_secs(DEFAULT_FORCED_REFRESH_INTERVAL)),
+            last_forced_refresh: Arc::default(),
+            persistent_cache: None,
+            cache_options: CacheOptions::default(),
         }
     }
 
@@ -58,12 +84,16 @@ impl AsyncClient {
         let client_id = client_id.to_string();
 
         if !client_id.is_empty() {
-            // check if client_id exists?
-            if self.client_ids.read().await.contains(&client_id) {
-                return
-            }
+            self.client_ids.rcu(|client_ids| {
+                let mut client_ids = Vec::clone(client_ids);
+
+                // check if client_id exists?
+                if !client_ids.contains(&client_id) {
+                    client_ids.push(client_id.clone());
+                }
 
-            self.client_ids.write().await.push(client_id)
+                client_ids
+            });
         }
     }
 
@@ -74,8 +104,12 @@ impl AsyncClient {
         let to_delete = client_id.as_ref();
 
         if !to_delete.is_empty() {
-            let mut client_ids = self.client_ids.write().await;
-            client_ids.retain(|id| id != to_delete)
+            self.client_ids.rcu(|client_ids| {
+                let mut client_ids = Vec::clone(client_ids);
+                client_ids.retain(|id| id != to_delete);
+
+                client_ids
+            });
         }
     }
 
@@ -89,49 +123,280 @@ impl AsyncClient {
         self
     }
 
+    /// Set the url to fetch certs (JWKS, or X.509 certificates in PEM) from. Default is `https://www.googleapis.com/oauth2/v3/certs`.
+    pub fn certs_url<S: ToString>(mut self, url: S) -> Self {
+        self.certs_url = url.to_string();
+
+        self
+    }
+
+    /// Set the source of certs, e.g. [crate::StaticCertSource] or [crate::FileCertSource] for offline verification.
+    ///
+    /// By default, certs are fetched from the certs url (see [Self::certs_url]) with the timeout of this client.
+    pub fn cert_source<C: AsyncCertSource + 'static>(mut self, source: C) -> Self {
+        self.cert_source = Some(Arc::new(source));
+
+        self
+    }
+
+    /// Set the minimum interval between forced refreshes.
+    ///
+    /// When the `kid` of a token is not found in the cached certs (which are not expired yet),
+    /// certs are fetched again, in case Google rotated the keys. To avoid being abused by tokens with random `kid`s,
+    /// it happens at most once per interval. Default is 60 seconds, and `None` disables forced refreshes.
+    pub fn forced_refresh_interval<D: Into<Option<Duration>>>(mut self, d: D) -> Self {
+        self.forced_refresh_interval = d.into();
+
+        self
+    }
+
+    /// Set the [CacheOptions], which controls how long fetched certs are cached.
+    pub fn cache_options(mut self, options: CacheOptions) -> Self {
+        self.cache_options = options;
+
+        self
+    }
+
+    /// Persist certs into the file `path`, so they survive restarts.
+    ///
+    /// Certs are loaded from the file now, and saved into it after each successful refresh.
+    /// If the file is expired or corrupted, certs are fetched from the cert source as usual.
+    pub fn persistent_cache<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        let path = path.into();
+
+        if let Some(certs) = Certs::load_from_cache(&path) {
+            self.cached_certs = Arc::new(ArcSwap::from_pointee(certs));
+        }
+
+        self.persistent_cache = Some(path);
+
+        self
+    }
+
+    /// Set the url of userinfo api, which is used in validating `access_token`.
+    /// Default is `https://www.googleapis.com/oauth2/v3/userinfo`.
+    pub fn userinfo_url<S: ToString>(mut self, url: S) -> Self {
+        self.userinfo_url = url.to_string();
+
+        self
+    }
+
+    /// Set the default [ValidationOptions] used by [Self::validate_id_token].
+    pub fn validation_options(mut self, options: ValidationOptions) -> Self {
+        self.options = options;
+
+        self
+    }
+
     /// Do verification with `id_token`. If success, return the user data.
     pub async fn validate_id_token<S>(&self, token: S) -> MyResult<GooglePayload>
     where S: AsRef<str>
+    {
+        self.validate_id_token_with_options(token, &self.options).await
+    }
+
+    /// Do verification with `id_token`, using `options` instead of the default [ValidationOptions] of this client.
+    pub async fn validate_id_token_with_options<S>(&self, token: S, options: &ValidationOptions) -> MyResult<GooglePayload>
+    where S: AsRef<str>
+    {
+        self.validate_id_token_as_with_options(token, options).await
+    }
+
+    /// Do verification with `id_token`, and deserialize the payload as `T`.
+    ///
+    /// It is useful when you need extra (or custom) claims, which are not listed in [GooglePayload].
+    pub async fn validate_id_token_as<T, S>(&self, token: S) -> MyResult<T>
+    where
+        T: IDTokenClaims,
+        S: AsRef<str>,
+    {
+        self.validate_id_token_as_with_options(token, &self.options).await
+    }
+
+    /// Same as [Self::validate_id_token_as], but using `options` instead of the default [ValidationOptions] of this client.
+    pub async fn validate_id_token_as_with_options<T, S>(&self, token: S, options: &ValidationOptions) -> MyResult<T>
+    where
+        T: IDTokenClaims,
+        S: AsRef<str>,
     {
         let token = token.as_ref();
-        let client_ids = self.client_ids.read().await;
+        let client_ids = self.client_ids.load();
 
         let parser = JwtParser::parse(token)?;
-        id_token::validate_info(&*client_ids, &parser)?;
+        id_token::validate_info(&**client_ids, &parser, options)?;
 
-        let cert = self.get_cert(&parser.header.alg, &parser.header.kid).await?;
-        id_token::do_validate(&cert, &parser)?;
+        let key = self.get_key(&parser.header.alg, &parser.header.kid).await?;
+        id_token::do_validate(&key, &parser)?;
 
         Ok(parser.payload)
     }
 
-    async fn get_cert(&self, alg: &str, kid: &str) -> MyResult<Cert> {
+    /// Do verification with `id_token`, and require its `nonce` claim to be equal to `nonce`.
+    pub async fn validate_id_token_with_nonce<S, N>(&self, token: S, nonce: N) -> MyResult<GooglePayload>
+    where
+        S: AsRef<str>,
+        N: ToString,
+    {
+        let options = self.options.clone().nonce(nonce);
+
+        self.validate_id_token_with_options(token, &options).await
+    }
+
+    /// Do verification with a Google-signed `id_token` of a service account, e.g. when Cloud Run, Cloud Functions,
+    /// Cloud Scheduler or Cloud Tasks call this service. The client id of this client is the audience of the token,
+    /// which is usually the url of this service.
+    ///
+    /// The `email` of the token must be listed in [ValidationOptions::service_accounts], and be verified.
+    /// Note that any Google account can get a token for any audience, so every token is rejected if no service account is allowed.
+    /// [ValidationOptions::hosted_domains] and [ValidationOptions::reject_consumer_accounts] are not checked.
+    pub async fn validate_service_id_token<S>(&self, token: S) -> MyResult<ServiceIdTokenPayload>
+        where S: AsRef<str>
+    {
+        self.validate_service_id_token_with_options(token, &self.options).await
+    }
+
+    /// Same as [Self::validate_service_id_token], but using `options` instead of the default [ValidationOptions] of this client.
+    pub async fn validate_service_id_token_with_options<S>(&self, token: S, options: &ValidationOptions) -> MyResult<ServiceIdTokenPayload>
+        where S: AsRef<str>
+    {
+        // service accounts have no `hd`, so the hosted domain policy would reject every token.
+        let options = &options.without_hosted_domains();
+        let payload: ServiceIdTokenPayload = self.validate_id_token_as_with_options(token, options).await?;
+
+        // the audience is never skipped here, even if this client has no client id.
+        if self.client_ids.load().is_empty() {
+            Err(IDTokenClientIDNotFoundError::new(&payload.aud, Vec::<String>::new()))?
+        }
+
+        policy::validate_service_account(payload.email.as_deref(), payload.email_verified, options)?;
+
+        Ok(payload)
+    }
+
+    async fn get_key(&self, alg: &str, kid: &str) -> MyResult<Arc<VerifyingKey>> {
+        let mut forced = false;
+
         {
-            let cached_certs = self.cached_certs.read().await;
-            if !cached_certs.need_refresh() {
+            let cached_certs = self.cached_certs.load();
+            let usable = !cached_certs.need_refresh()
+                // stale-while-revalidate: a running background refresher will replace them soon,
+                // but certs expired for too long (e.g. the refresher keeps failing) are fetched here.
+                || (self.background_refreshers.load(Ordering::Relaxed) > 0
+                    && cached_certs.is_stale_within(Duration::from_secs(BACKGROUND_REFRESH_GRACE)));
+
+            if usable {
                 debug!("certs: use cache");
-                return cached_certs.find_cert(alg, kid);
+
+                match cached_certs.find_key(alg, kid) {
+                    Err(Error::IDTokenCertNotFoundError(_)) if self.allow_forced_refresh() => {
+                        debug!("certs: kid {} not found in cache, force refresh", kid);
+                        forced = true;
+                    }
+                    result => return result,
+                }
+            }
+        }
+
+        // single-flight: only one fetch is in progress, other callers wait for its result.
+        let _refreshing = self.refresh_lock.lock().await;
+
+        {
+            // double-check: certs may be refreshed by another caller while we were waiting.
+            let cached_certs = self.cached_certs.load();
+            if !cached_certs.need_refresh() {
+                match cached_certs.find_key(alg, kid) {
+                    // a forced refresh is still needed if the kid is missing.
+                    Err(Error::IDTokenCertNotFoundError(_)) if forced => {}
+                    result => {
+                        debug!("certs: refreshed by another caller");
+                        return result;
+                    }
+                }
             }
         }
 
         debug!("certs: try to fetch new certs");
 
-        let mut cached_certs = self.cached_certs.write().await;
+        // fetch while holding only the single-flight lock: validations keep reading the previous certs,
+        // until the new ones are stored.
+        let certs = fetch(&*self.source(), &self.cached_certs, self.cache_options).await?;
 
-        // refresh certs here...
-        let resp = ca.get(GOOGLE_SA_CERTS_URL)
-            .timeout(self.timeout)
-            .send()
-            .await?;
+        certs.save_to_cache(self.persistent_cache.as_deref());
+
+        let key = certs.find_key(alg, kid);
+        self.cached_certs.store(Arc::new(certs));
 
-        // parse the response header `age` and `max-age`.
-        let max_age = utils::parse_max_age_from_async_resp(&resp);
+        key
+    }
 
-        let info = resp.bytes().await?;
-        *cached_certs = serde_json::from_slice(&info)?;
+    fn allow_forced_refresh(&self) -> bool {
+        let Some(interval) = self.forced_refresh_interval else {
+            return false;
+        };
+
+        let mut last = self.last_forced_refresh.lock().unwrap_or_else(PoisonError::into_inner);
+        if last.is_some_and(|last| last.elapsed() < interval) {
+            return false;
+        }
 
-        cached_certs.set_cache_until(Instant::now().add(Duration::from_secs(max_age)));
-        cached_certs.find_cert(alg, kid)
+        *last = Some(Instant::now());
+        true
+    }
+
+    fn source(&self) -> Arc<dyn AsyncCertSource> {
+        match &self.cert_source {
+            Some(source) => source.clone(),
+            None => Arc::new(HttpCertSource::new(&self.certs_url).timeout(self.timeout)),
+        }
+    }
+
+    /// Fetch certs from the cert source now, and replace the cached certs.
+    ///
+    /// The cached certs are still used by other validations while fetching.
+    pub async fn refresh_certs(&self) -> MyResult<()> {
+        refresh(&*self.source(), &self.cached_certs, &self.refresh_lock, self.persistent_cache.as_deref(), self.cache_options).await
+    }
+
+    /// Spawn a tokio task, which refreshes the cached certs shortly before they expire.
+    ///
+    /// Once the refresher is spawned, validations never wait for fetching certs (except the first time),
+    /// but keep using the previous certs until the new ones arrive (for at most 5 minutes after they expire).
+    /// If fetching fails, it will be retried later.
+    ///
+    /// The task exits on its next wake-up after this client (and all its clones) are dropped,
+    /// i.e. shortly before the cached certs expire, or within 10 seconds after a failure.
+    #[cfg(feature = "background-refresh")]
+    pub fn spawn_background_refresh(&self) -> tokio::task::JoinHandle<()> {
+        let alive = RefresherGuard::new(self.background_refreshers.clone());
+
+        let source = self.source();
+        let cached_certs = Arc::downgrade(&self.cached_certs);
+        let refresh_lock = self.refresh_lock.clone();
+        let persistent_cache = self.persistent_cache.clone();
+        let cache_options = self.cache_options;
+
+        tokio::spawn(async move {
+            // dropped when the refresher stops for any reason.
+            let _alive = alive;
+            let mut wait = Duration::ZERO;
+
+            loop {
+                tokio::time::sleep(wait).await;
+
+                let Some(cached_certs) = cached_certs.upgrade() else {
+                    debug!("certs: client dropped, stop background refresh");
+                    return;
+                };
+
+                wait = match refresh(&*source, &cached_certs, &refresh_lock, persistent_cache.as_deref(), cache_options).await {
+                    Ok(_) => cached_certs.load().refresh_ahead_wait(),
+                    Err(e) => {
+                        log::warn!("certs: background refresh failed: {}", e);
+                        Duration::from_secs(crate::BACKGROUND_REFRESH_RETRY)
+                    }
+                };
+            }
+        })
     }
 
     /// Try to validate access token. If success, return the user info.
@@ -140,14 +405,17 @@ impl AsyncClient {
     {
         let token = token.as_ref();
 
-        let info = ca.get(format!("{}?access_token={}", GOOGLE_OAUTH_V3_USER_INFO_API, token))
+        let info = ca.get(format!("{}?access_token={}", self.userinfo_url, token))
             .timeout(self.timeout)
             .send()
             .await?
             .bytes()
             .await?;
 
-        Ok(serde_json::from_slice(&info)?)
+        let payload: GoogleAccessTokenPayload = serde_json::from_slice(&info)?;
+        policy::validate_hosted_domain(payload.hd.as_deref(), &self.options)?;
+
+        Ok(payload)
     }
 }
 
@@ -156,3 +424,174 @@ impl Default for AsyncClient {
         Self::new_with_vec::<&[_; 0], &'static str>(&[])
     }
 }
+
+/// Fetch certs under the single-flight `refresh_lock`, then store them at once.
+/// Validations with usable certs do not wait for the lock, so they keep reading the previous certs while fetching.
+async fn refresh(source: &dyn AsyncCertSource, cached_certs: &ArcSwap<Certs>, refresh_lock: &AsyncMutex<()>, persistent_cache: Option<&Path>, cache_options: CacheOptions) -> MyResult<()> {
+    let _refreshing = refresh_lock.lock().await;
+
+    debug!("certs: refresh");
+
+    let certs = fetch(source, cached_certs, cache_options).await?;
+    certs.save_to_cache(persistent_cache);
+    cached_certs.store(Arc::new(certs));
+
+    Ok(())
+}
+
+/// Fetch certs from `source`, revalidating the cached certs if there are any, and apply `cache_options`.
+async fn fetch(source: &dyn AsyncCertSource, cached_certs: &ArcSwap<Certs>, cache_options: CacheOptions) -> MyResult<Certs> {
+    let cached = cached_certs.load_full();
+
+    let mut certs = if cached.keys().is_empty() {
+        source.fetch_certs().await?
+    } else {
+        source.revalidate_certs(&cached).await?
+    };
+
+    cache_options.apply(&mut certs);
+    certs.prepare_keys();
+
+    Ok(certs)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use base64::Engine;
+    use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+    use p256::ecdsa::{Signature, SigningKey};
+    use p256::ecdsa::signature::Signer;
+    use crate::{Cert, StaticCertSource};
+    use crate::cert_source::CertsFuture;
+    use super::*;
+
+    /// A source of empty certs, counting how many times certs are fetched. Each fetch takes `delay`.
+    #[derive(Debug, Default, Clone)]
+    struct CountingSource {
+        fetches: Arc<AtomicUsize>,
+        delay: Duration,
+    }
+
+    impl CountingSource {
+        fn fetches(&self) -> usize {
+            self.fetches.load(Ordering::SeqCst)
+        }
+    }
+
+    impl AsyncCertSource for CountingSource {
+        fn fetch_certs(&self) -> CertsFuture<'_> {
+            Box::pin(async move {
+                self.fetches.fetch_add(1, Ordering::SeqCst);
+                tokio::time::sleep(self.delay).await;
+
+                let mut certs = Certs::default();
+                certs.set_cache_until(Instant::now() + Duration::from_secs(3600));
+
+                Ok(certs)
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn forced_refresh_rate_limited() {
+        let source = CountingSource::default();
+        let client = AsyncClient::default().cert_source(source.clone());
+
+        for i in 0..5 {
+            let result = client.get_key("RS256", &format!("unknown-{}", i)).await;
+            assert!(matches!(result, Err(Error::IDTokenCertNotFoundError(_))));
+        }
+
+        // the first fetch, and only one forced refresh within the interval.
+        assert_eq!(source.fetches(), 2);
+    }
+
+    #[tokio::test]
+    async fn forced_refresh_disabled() {
+        let source = CountingSource::default();
+        let client = AsyncClient::default()
+            .cert_source(source.clone())
+            .forced_refresh_interval(None);
+
+        for i in 0..5 {
+            let result = client.get_key("RS256", &format!("unknown-{}", i)).await;
+            assert!(matches!(result, Err(Error::IDTokenCertNotFoundError(_))));
+        }
+
+        assert_eq!(source.fetches(), 1);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn single_flight() {
+        let source = CountingSource { delay: Duration::from_millis(100), ..Default::default() };
+        // no forced refresh, so callers after the fetch do not fetch again for the missing kid.
+        let client = AsyncClient::default()
+            .cert_source(source.clone())
+            .forced_refresh_interval(None);
+
+        let callers: Vec<_> = (0..8)
+            .map(|_| {
+                let client = client.clone();
+                tokio::spawn(async move { client.get_key("RS256", "k1").await })
+            })
+            .collect();
+
+        for caller in callers {
+            assert!(matches!(caller.await.unwrap(), Err(Error::IDTokenCertNotFoundError(_))));
+        }
+
+        assert_eq!(source.fetches(), 1);
+    }
+
+    /// A Google-signed (ES256, with a random key) service `id_token` for `aud`, and the certs to verify it.
+    fn service_id_token(aud: &str) -> (String, Certs) {
+        let key = SigningKey::random(&mut rsa::rand_core::OsRng);
+        let point = key.verifying_key().to_encoded_point(false);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+        let msg = format!(
+            "{}.{}",
+            BASE64_URL_SAFE_NO_PAD.encode(serde_json::json!({"alg": "ES256", "kid": "k1"}).to_string()),
+            BASE64_URL_SAFE_NO_PAD.encode(serde_json::json!({
+                "aud": aud,
+                "iss": "https://accounts.google.com",
+                "sub": "1234",
+                "email": "caller@my-project.iam.gserviceaccount.com",
+                "email_verified": true,
+                "iat": now,
+                "exp": now + 3600,
+            }).to_string()),
+        );
+        let sig: Signature = key.sign(msg.as_bytes());
+
+        let certs = Certs::new(vec![Cert {
+            kid: "k1".to_string(),
+            alg: "ES256".to_string(),
+            kty: "EC".to_string(),
+            e: String::new(),
+            n: String::new(),
+            crv: Some("P-256".to_string()),
+            x: point.x().map(|x| BASE64_URL_SAFE_NO_PAD.encode(x)),
+            y: point.y().map(|y| BASE64_URL_SAFE_NO_PAD.encode(y)),
+            not_after: None,
+        }]);
+
+        (format!("{}.{}", msg, BASE64_URL_SAFE_NO_PAD.encode(sig.to_bytes())), certs)
+    }
+
+    #[tokio::test]
+    async fn service_id_token_without_hosted_domain() {
+        let (token, certs) = service_id_token("https://my-service.run.app");
+        let client = AsyncClient::new("https://my-service.run.app")
+            .cert_source(StaticCertSource::new(certs))
+            .validation_options(ValidationOptions::new()
+                .service_accounts(["caller@my-project.iam.gserviceaccount.com"])
+                .hosted_domains(["example.com"])
+                .reject_consumer_accounts(true));
+
+        // service accounts have no `hd`, so the hosted domain policy applies to user tokens only.
+        assert!(matches!(client.validate_id_token(&token).await, Err(Error::HostedDomainNotAllowedError(_))));
+        assert!(client.validate_service_id_token(&token).await.is_ok());
+    }
+}
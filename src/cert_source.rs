@@ -0,0 +1,269 @@
+use std::fmt::Debug;
+use std::future::Future;
+use std::ops::Add;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+use log::debug;
+use reqwest::StatusCode;
+use reqwest::header::{IF_MODIFIED_SINCE, IF_NONE_MATCH};
+use crate::{Certs, DEFAULT_TIMEOUT, GOOGLE_SA_CERTS_URL, MyResult, utils};
+
+/// The future returned by [AsyncCertSource::fetch_certs].
+pub type CertsFuture<'a> = Pin<Box<dyn Future<Output = MyResult<Certs>> + Send + 'a>>;
+
+/// `CertSource` provides certs for the blocking client, see `Client::cert_source` (feature `blocking`).
+///
+/// The returned [Certs] should have `cache_until` set (see [Certs::set_cache_until]),
+/// otherwise `fetch_certs` will be called again for the next validation.
+pub trait CertSource: Debug + Send + Sync {
+    fn fetch_certs(&self) -> MyResult<Certs>;
+
+    /// Fetch certs again, when `cached` certs (which came from this source) are expired.
+    /// It can be used to do conditional requests. By default, it is the same as [CertSource::fetch_certs].
+    fn revalidate_certs(&self, cached: &Certs) -> MyResult<Certs> {
+        let _ = cached;
+        self.fetch_certs()
+    }
+}
+
+/// `AsyncCertSource` provides certs for the async client, see [crate::AsyncClient::cert_source].
+///
+/// The returned [Certs] should have `cache_until` set (see [Certs::set_cache_until]),
+/// otherwise `fetch_certs` will be called again for the next validation.
+pub trait AsyncCertSource: Debug + Send + Sync {
+    fn fetch_certs(&self) -> CertsFuture<'_>;
+
+    /// Fetch certs again, when `cached` certs (which came from this source) are expired.
+    /// It can be used to do conditional requests. By default, it is the same as [AsyncCertSource::fetch_certs].
+    fn revalidate_certs<'a>(&'a self, cached: &'a Certs) -> CertsFuture<'a> {
+        let _ = cached;
+        self.fetch_certs()
+    }
+}
+
+/// `HttpCertSource` fetches certs (JWKS, or X.509 certificates in PEM) from a url. It is the default source of clients.
+///
+/// The certs are cached according to the `Cache-Control`, `Age` and `Expires` headers.
+/// When revalidating, `If-None-Match` and `If-Modified-Since` are sent, and a `304 Not Modified`
+/// response extends the cached certs.
+#[derive(Debug, Clone)]
+pub struct HttpCertSource {
+    url: String,
+    timeout: Duration,
+}
+
+impl HttpCertSource {
+    /// Create a new http source, fetching certs from `url`.
+    pub fn new<S: ToString>(url: S) -> Self {
+        Self {
+            url: url.to_string(),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT),
+        }
+    }
+
+    /// Set the timeout of fetching certs.
+    /// Default timeout is 5 seconds. Zero timeout will be ignored.
+    pub fn timeout(mut self, d: Duration) -> Self {
+        if !d.is_zero() {
+            self.timeout = d;
+        }
+
+        self
+    }
+
+    async fn fetch_async(&self, cached: Option<&Certs>) -> MyResult<Certs> {
+        debug!("certs: fetch from {}", &self.url);
+
+        let mut req = crate::async_client::ca.get(&self.url)
+            .timeout(self.timeout);
+
+        if let Some(etag) = cached.and_then(|c| c.etag()) {
+            req = req.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = cached.and_then(|c| c.last_modified()) {
+            req = req.header(IF_MODIFIED_SINCE, last_modified);
+        }
+
+        let resp = req.send().await?;
+
+        let ttl = utils::parse_ttl_from_headers(resp.headers());
+        let (etag, last_modified) = utils::parse_validators_from_headers(resp.headers());
+
+        let mut certs = match cached {
+            Some(cached) if resp.status() == StatusCode::NOT_MODIFIED => {
+                debug!("certs: not modified");
+                cached.clone()
+            }
+            _ => {
+                let info = resp.error_for_status()?.bytes().await?;
+                serde_json::from_slice(&info)?
+            }
+        };
+
+        Self::update(&mut certs, ttl, etag, last_modified);
+
+        Ok(certs)
+    }
+
+    #[cfg(feature = "blocking")]
+    fn fetch_blocking(&self, cached: Option<&Certs>) -> MyResult<Certs> {
+        debug!("certs: fetch from {}", &self.url);
+
+        let mut req = crate::client::cb.get(&self.url)
+            .timeout(self.timeout);
+
+        if let Some(etag) = cached.and_then(|c| c.etag()) {
+            req = req.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = cached.and_then(|c| c.last_modified()) {
+            req = req.header(IF_MODIFIED_SINCE, last_modified);
+        }
+
+        let resp = req.send()?;
+
+        let ttl = utils::parse_ttl_from_headers(resp.headers());
+        let (etag, last_modified) = utils::parse_validators_from_headers(resp.headers());
+
+        let mut certs = match cached {
+            Some(cached) if resp.status() == StatusCode::NOT_MODIFIED => {
+                debug!("certs: not modified");
+                cached.clone()
+            }
+            _ => {
+                let info = resp.error_for_status()?.bytes()?;
+                serde_json::from_slice(&info)?
+            }
+        };
+
+        Self::update(&mut certs, ttl, etag, last_modified);
+
+        Ok(certs)
+    }
+
+    fn update(certs: &mut Certs, ttl: u64, etag: Option<String>, last_modified: Option<String>) {
+        // a `304 Not Modified` response may omit the validators, keep the previous ones then.
+        if etag.is_some() || last_modified.is_some() {
+            certs.set_validators(etag, last_modified);
+        }
+
+        certs.set_cache_until(Instant::now().add(Duration::from_secs(ttl)));
+    }
+}
+
+impl Default for HttpCertSource {
+    fn default() -> Self {
+        Self::new(GOOGLE_SA_CERTS_URL)
+    }
+}
+
+impl AsyncCertSource for HttpCertSource {
+    fn fetch_certs(&self) -> CertsFuture<'_> {
+        Box::pin(self.fetch_async(None))
+    }
+
+    fn revalidate_certs<'a>(&'a self, cached: &'a Certs) -> CertsFuture<'a> {
+        Box::pin(self.fetch_async(Some(cached)))
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl CertSource for HttpCertSource {
+    fn fetch_certs(&self) -> MyResult<Certs> {
+        self.fetch_blocking(None)
+    }
+
+    fn revalidate_certs(&self, cached: &Certs) -> MyResult<Certs> {
+        self.fetch_blocking(Some(cached))
+    }
+}
+
+/// `StaticCertSource` always provides the same in-memory certs, e.g. for offline verification or tests.
+#[derive(Debug, Clone)]
+pub struct StaticCertSource {
+    certs: Certs,
+}
+
+impl StaticCertSource {
+    /// The certs never change, so they are provided again only once a day.
+    const CACHE_DURATION: Duration = Duration::from_secs(24 * 60 * 60);
+
+    pub fn new(certs: Certs) -> Self {
+        Self { certs }
+    }
+
+    fn certs(&self) -> Certs {
+        let mut certs = self.certs.clone();
+        certs.set_cache_until(Instant::now().add(Self::CACHE_DURATION));
+
+        certs
+    }
+}
+
+impl From<Certs> for StaticCertSource {
+    #[inline]
+    fn from(certs: Certs) -> Self {
+        Self::new(certs)
+    }
+}
+
+impl AsyncCertSource for StaticCertSource {
+    fn fetch_certs(&self) -> CertsFuture<'_> {
+        Box::pin(async move { Ok(self.certs()) })
+    }
+}
+
+impl CertSource for StaticCertSource {
+    fn fetch_certs(&self) -> MyResult<Certs> {
+        Ok(self.certs())
+    }
+}
+
+/// `FileCertSource` reads certs from a JSON file on disk, either a JWKS or a map of X.509 certificates in PEM, see [Certs].
+///
+/// The file is read again when the reload interval is passed, so the certs can be rotated by replacing the file.
+#[derive(Debug, Clone)]
+pub struct FileCertSource {
+    path: PathBuf,
+    reload_interval: Duration,
+}
+
+impl FileCertSource {
+    /// Create a new file source, reading certs from `path`.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            reload_interval: Duration::from_secs(5 * 60),
+        }
+    }
+
+    /// Set the interval of reading the file again. Default is 5 minutes.
+    pub fn reload_interval(mut self, d: Duration) -> Self {
+        self.reload_interval = d;
+        self
+    }
+
+    fn read(&self) -> MyResult<Certs> {
+        debug!("certs: read from {:?}", &self.path);
+
+        let info = std::fs::read(&self.path)?;
+        let mut certs: Certs = serde_json::from_slice(&info)?;
+
+        certs.set_cache_until(Instant::now().add(self.reload_interval));
+
+        Ok(certs)
+    }
+}
+
+impl AsyncCertSource for FileCertSource {
+    /// Note: the file is read synchronously, since it is expected to be small.
+    fn fetch_certs(&self) -> CertsFuture<'_> {
+        Box::pin(async move { self.read() })
+    }
+}
+
+impl CertSource for FileCertSource {
+    fn fetch_certs(&self) -> MyResult<Certs> {
+        self.read()
+    }
+}
@@ -1,12 +1,18 @@
 #[cfg(not(feature = "wasm"))]
-use std::time::Instant;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 #[cfg(feature = "wasm")]
-use web_time::Instant;
+use web_time::{Instant, SystemTime, UNIX_EPOCH};
+use std::collections::HashMap;
+use std::sync::Arc;
 use log::debug;
 use serde::{Deserialize, Serialize};
-use crate::{IDTokenCertNotFoundError, MyResult};
+use crate::{Algorithm, CertInvalidError, CertsFormatInvalidError, IDTokenCertNotFoundError, MyResult};
+use crate::validate::key::VerifyingKey;
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+/// Certs can be deserialized from a JWKS (`{"keys": [...]}`), or from a map of X.509 certificates in PEM
+/// (`{"<kid>": "-----BEGIN CERTIFICATE-----..."}`), see [Certs::from_pem_map].
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[serde(try_from = "CertsFormat")]
 pub struct Certs {
     keys: Vec<Cert>,
 
@@ -15,28 +21,191 @@ pub struct Certs {
     /// 2. if let Some(time) = cache_until, current time > time
     #[serde(skip)]
     cache_until: Option<Instant>,
+
+    /// `ETag` and `Last-Modified` of the response, used in conditional requests when refreshing.
+    #[serde(skip)]
+    etag: Option<String>,
+    #[serde(skip)]
+    last_modified: Option<String>,
+
+    /// Keys decoded from `keys`, by `kid` and algorithm. They are built once after each refresh,
+    /// so validations only need to verify the signature.
+    #[serde(skip)]
+    verifying_keys: HashMap<(String, Algorithm), Arc<VerifyingKey>>,
+}
+
+/// The formats of certs published by Google: a JWKS when there is a `keys` field,
+/// otherwise a map of `kid` to X.509 certificate in PEM.
+type CertsFormat = serde_json::Map<String, serde_json::Value>;
+
+const PEM_CERTIFICATE_BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+
+impl TryFrom<CertsFormat> for Certs {
+    type Error = crate::Error;
+
+    fn try_from(mut format: CertsFormat) -> MyResult<Self> {
+        if let Some(keys) = format.remove("keys") {
+            return Ok(Certs::new(serde_json::from_value(keys)?));
+        }
+
+        if format.is_empty() {
+            Err(CertsFormatInvalidError::new("no `keys`, and no certificate"))?
+        }
+
+        let pems = format
+            .into_iter()
+            .map(|(kid, pem)| match pem {
+                serde_json::Value::String(pem) if pem.trim_start().starts_with(PEM_CERTIFICATE_BEGIN) => Ok((kid, pem)),
+                _ => Err(CertsFormatInvalidError::new(format!("no `keys`, and `{}` is not a certificate in PEM", kid))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Certs::from_pem_map(pems)
+    }
+}
+
+/// The format of certs in the persistent cache, see [Certs::save_to_file].
+#[cfg(not(feature = "wasm"))]
+#[derive(Serialize, Deserialize)]
+struct PersistedCerts {
+    /// Unix time (in seconds) when the certs expire.
+    expires_at: u64,
+    #[serde(flatten)]
+    certs: Certs,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Cert {
     pub kid: String,
-    pub e: String,
+    /// `alg` is optional in JWK. When it is empty, the cert can be used by any algorithm matching `kty`.
+    #[serde(default)]
     pub alg: String,
     pub kty: String,
+
+    // These fields are used by RSA keys (`kty` = `RSA`).
+    #[serde(default)]
+    pub e: String,
+    #[serde(default)]
     pub n: String,
+
+    // These fields are used by EC keys (`kty` = `EC`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub y: Option<String>,
+
+    /// Unix time (in seconds) after which the cert must not be used, e.g. the end of the validity of an X.509 certificate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_after: Option<u64>,
 }
 
 impl Certs {
+    /// Create certs from keys, e.g. for offline verification. The certs need refresh until `cache_until` is set.
+    pub fn new(keys: Vec<Cert>) -> Self {
+        Self {
+            keys,
+            ..Default::default()
+        }
+    }
+
+    /// Create certs from a map of `kid` to X.509 certificate in PEM, e.g. from
+    /// `https://www.googleapis.com/oauth2/v1/certs`.
+    ///
+    /// Certificates which cannot be parsed, or are not in their validity window, are skipped.
+    /// It fails when none of the certificates can be used.
+    pub fn from_pem_map<I, K, V>(pems: I) -> MyResult<Self>
+        where
+            I: IntoIterator<Item = (K, V)>,
+            K: AsRef<str>,
+            V: AsRef<str>,
+    {
+        let mut skipped = vec![];
+
+        let keys: Vec<Cert> = pems
+            .into_iter()
+            .filter_map(|(kid, pem)| match Cert::from_x509_pem(kid.as_ref(), pem.as_ref()) {
+                Ok(cert) => Some(cert),
+                Err(e) => {
+                    log::warn!("certs: skip x509 certificate: {}", e);
+                    skipped.push(kid.as_ref().to_string());
+                    None
+                }
+            })
+            .collect();
+
+        if keys.is_empty() {
+            Err(CertInvalidError::new(skipped.join(","), "no usable x509 certificate"))?
+        }
+
+        Ok(Self::new(keys))
+    }
+
+    #[inline]
+    pub fn keys(&self) -> &[Cert] {
+        &self.keys
+    }
+
     pub fn find_cert<T: AsRef<str>>(&self, alg: T, kid: T) -> MyResult<Cert> {
         let alg = alg.as_ref();
         let kid = kid.as_ref();
+        let algorithm: Algorithm = alg.parse()?;
 
-        match self.keys.iter().find(|cert| cert.alg == alg && cert.kid == kid) {
+        match self.find(algorithm, kid) {
             Some(cert ) => Ok(cert.clone()),
             None => Err(IDTokenCertNotFoundError::new(alg, kid))?,
         }
     }
 
+    fn find(&self, alg: Algorithm, kid: &str) -> Option<&Cert> {
+        self.keys.iter().find(|cert| cert.kid == kid && cert.is_usable_for(alg) && !cert.is_expired())
+    }
+
+    /// Find the decoded key of `kid` for `alg`. Keys missing from [Self::prepare_keys] are decoded on demand.
+    /// Expired certs are not found, even if their keys have been decoded.
+    pub(crate) fn find_key(&self, alg: &str, kid: &str) -> MyResult<Arc<VerifyingKey>> {
+        let algorithm: Algorithm = alg.parse()?;
+
+        let cert = match self.find(algorithm, kid) {
+            Some(cert) => cert,
+            None => Err(IDTokenCertNotFoundError::new(alg, kid))?,
+        };
+
+        if let Some(key) = self.verifying_keys.get(&(kid.to_string(), algorithm)) {
+            return Ok(key.clone());
+        }
+
+        Ok(Arc::new(VerifyingKey::from_cert(cert, algorithm)?))
+    }
+
+    /// Decode the keys of all certs, reusing the keys which have been decoded before.
+    /// Certs which cannot be decoded are skipped here, and fail when they are used. Expired certs are skipped too.
+    pub(crate) fn prepare_keys(&mut self) {
+        let mut verifying_keys = HashMap::new();
+
+        for cert in self.keys.iter().filter(|cert| !cert.is_expired()) {
+            for alg in Algorithm::ALL.iter().copied().filter(|alg| cert.is_usable_for(*alg)) {
+                let id = (cert.kid.clone(), alg);
+
+                let key = match self.verifying_keys.remove(&id) {
+                    Some(key) => key,
+                    None => match VerifyingKey::from_cert(cert, alg) {
+                        Ok(key) => Arc::new(key),
+                        Err(e) => {
+                            debug!("certs: cannot decode key {} for {}: {}", cert.kid, alg, e);
+                            continue;
+                        }
+                    },
+                };
+
+                verifying_keys.insert(id, key);
+            }
+        }
+
+        self.verifying_keys = verifying_keys;
+    }
+
     #[inline]
     pub fn set_cache_until<T>(&mut self, cache_until: T)
         where T: Into<Option<Instant>>
@@ -47,6 +216,132 @@ impl Certs {
         self.cache_until = cache_until;
     }
 
+    #[inline]
+    pub fn cache_until(&self) -> Option<Instant> {
+        self.cache_until
+    }
+
+    #[inline]
+    pub fn etag(&self) -> Option<&str> {
+        self.etag.as_deref()
+    }
+
+    #[inline]
+    pub fn last_modified(&self) -> Option<&str> {
+        self.last_modified.as_deref()
+    }
+
+    /// Set the `ETag` and `Last-Modified` of the response which the certs come from.
+    #[inline]
+    pub fn set_validators(&mut self, etag: Option<String>, last_modified: Option<String>) {
+        self.etag = etag;
+        self.last_modified = last_modified;
+    }
+
+    /// How long to wait before refreshing in background: shortly before `cache_until`, ahead by at most half of
+    /// the remaining cache duration, so short cache durations do not refresh in a loop. It is at least the retry interval.
+    #[cfg(any(feature = "blocking", feature = "background-refresh"))]
+    pub(crate) fn refresh_ahead_wait(&self) -> std::time::Duration {
+        use std::time::Duration;
+
+        let ttl = self
+            .cache_until
+            .map(|until| until.saturating_duration_since(Instant::now()))
+            .unwrap_or_default();
+
+        let ahead = Duration::from_secs(crate::BACKGROUND_REFRESH_AHEAD).min(ttl / 2);
+
+        (ttl - ahead).max(Duration::from_secs(crate::BACKGROUND_REFRESH_RETRY))
+    }
+
+    /// Save certs into `path`, with the expiry stored as unix time, so they can be loaded after restarts.
+    #[cfg(not(feature = "wasm"))]
+    pub fn save_to_file<P: AsRef<std::path::Path>>(&self, path: P) -> MyResult<()> {
+        let path = path.as_ref();
+        let ttl = self
+            .cache_until
+            .map(|until| until.saturating_duration_since(Instant::now()))
+            .unwrap_or_default();
+
+        let persisted = PersistedCerts {
+            expires_at: (SystemTime::now() + ttl).duration_since(UNIX_EPOCH)?.as_secs(),
+            certs: self.clone(),
+        };
+
+        // write to a temporary file in the same directory first, so a crash never leaves a half-written cache.
+        // The name is unique, so processes and clients sharing the cache do not write to the same temporary file.
+        let mut suffix = [0u8; 8];
+        let _ = getrandom::getrandom(&mut suffix);
+
+        let tmp = path.with_file_name(format!(
+            ".{}.{}.{:016x}.tmp",
+            path.file_name().unwrap_or_default().to_string_lossy(),
+            std::process::id(),
+            u64::from_le_bytes(suffix),
+        ));
+
+        if let Err(e) = std::fs::write(&tmp, serde_json::to_vec(&persisted)?).and_then(|_| std::fs::rename(&tmp, path)) {
+            let _ = std::fs::remove_file(&tmp);
+            Err(e)?
+        }
+
+        debug!("certs: saved to {:?}", path);
+        Ok(())
+    }
+
+    /// Load certs saved by [Certs::save_to_file]. If they are expired, [Certs::need_refresh] returns `true`.
+    #[cfg(not(feature = "wasm"))]
+    pub fn load_from_file<P: AsRef<std::path::Path>>(path: P) -> MyResult<Self> {
+        use std::time::Duration;
+
+        let path = path.as_ref();
+        let persisted: PersistedCerts = serde_json::from_slice(&std::fs::read(path)?)?;
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let mut certs = persisted.certs;
+
+        if persisted.expires_at > now {
+            certs.set_cache_until(Instant::now() + Duration::from_secs(persisted.expires_at - now));
+        }
+
+        certs.prepare_keys();
+
+        debug!("certs: loaded from {:?}", path);
+        Ok(certs)
+    }
+
+    /// Save certs into the persistent cache (if any) of a client. Failures are only logged.
+    #[cfg(not(feature = "wasm"))]
+    pub(crate) fn save_to_cache(&self, path: Option<&std::path::Path>) {
+        if let Some(path) = path {
+            if let Err(e) = self.save_to_file(path) {
+                log::warn!("certs: cannot save persistent cache {:?}: {}", path, e);
+            }
+        }
+    }
+
+    /// Load certs from the persistent cache of a client. Failures are only logged.
+    #[cfg(not(feature = "wasm"))]
+    pub(crate) fn load_from_cache(path: &std::path::Path) -> Option<Self> {
+        match Self::load_from_file(path) {
+            Ok(certs) => Some(certs),
+            Err(crate::Error::IoError(e)) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => {
+                log::warn!("certs: cannot load persistent cache {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// Whether the certs have been expired for less than `grace`,
+    /// so they can still be used while a background refresher is replacing them.
+    #[cfg(not(feature = "wasm"))]
+    pub(crate) fn is_stale_within(&self, grace: std::time::Duration) -> bool {
+        !self.keys.is_empty() && self
+            .cache_until
+            .is_some_and(|until| Instant::now() < until + grace)
+    }
+
     #[inline]
     pub fn need_refresh(&self) -> bool {
         self
@@ -55,3 +350,311 @@ impl Certs {
             .unwrap_or(true)
     }
 }
+
+impl Cert {
+    /// Create an RS256 cert from an X.509 certificate in PEM, which must contain an RSA public key,
+    /// and be in its validity window now. The end of the validity is kept as `not_after`.
+    pub fn from_x509_pem(kid: &str, pem: &str) -> MyResult<Self> {
+        use base64::Engine;
+        use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+        use rsa::RsaPublicKey;
+        use rsa::pkcs8::DecodePublicKey;
+        use rsa::traits::PublicKeyParts;
+        use x509_cert::Certificate;
+        use x509_cert::der::{DecodePem, Encode};
+
+        let invalid = |reason: String| CertInvalidError::new(kid, reason);
+
+        let certificate = Certificate::from_pem(pem)
+            .map_err(|e| invalid(format!("invalid x509 certificate: {}", e)))?;
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+        let validity = &certificate.tbs_certificate.validity;
+
+        if now < validity.not_before.to_unix_duration() || now > validity.not_after.to_unix_duration() {
+            Err(invalid(format!("x509 certificate is only valid from {} to {}", validity.not_before, validity.not_after)))?
+        }
+
+        let spki = certificate.tbs_certificate.subject_public_key_info
+            .to_der()
+            .map_err(|e| invalid(format!("invalid public key: {}", e)))?;
+
+        let pk = RsaPublicKey::from_public_key_der(&spki)
+            .map_err(|e| invalid(format!("not an RSA public key: {}", e)))?;
+
+        Ok(Self {
+            kid: kid.to_string(),
+            alg: Algorithm::RS256.as_str().to_string(),
+            kty: "RSA".to_string(),
+            e: BASE64_URL_SAFE_NO_PAD.encode(pk.e().to_bytes_be()),
+            n: BASE64_URL_SAFE_NO_PAD.encode(pk.n().to_bytes_be()),
+            crv: None,
+            x: None,
+            y: None,
+            not_after: Some(validity.not_after.to_unix_duration().as_secs()),
+        })
+    }
+
+    /// Check whether the cert is past its `not_after`.
+    pub fn is_expired(&self) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|now| now.as_secs())
+            .unwrap_or_default();
+
+        self.not_after.is_some_and(|not_after| now > not_after)
+    }
+
+    /// Check whether the cert can be used with `alg`:
+    /// the `alg` of the cert (if provided) and the key type (and curve, for EC keys) must match.
+    pub fn is_usable_for(&self, alg: Algorithm) -> bool {
+        if !self.alg.is_empty() && self.alg != alg.as_str() {
+            return false;
+        }
+
+        if self.kty != alg.key_type() {
+            return false;
+        }
+
+        match alg {
+            Algorithm::ES256 => self.crv.as_deref() == Some("P-256"),
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "wasm"))]
+    use std::time::Duration;
+    use super::*;
+
+    #[test]
+    #[cfg(any(feature = "blocking", feature = "background-refresh"))]
+    fn refresh_ahead_of_short_ttl() {
+        let wait_for = |ttl: u64| {
+            let mut certs = Certs::default();
+            certs.set_cache_until(Instant::now() + Duration::from_secs(ttl));
+
+            certs.refresh_ahead_wait().as_secs_f64()
+        };
+
+        // never below the retry interval, even if the certs are not cached at all.
+        assert_eq!(wait_for(0), crate::BACKGROUND_REFRESH_RETRY as f64);
+        assert_eq!(Certs::default().refresh_ahead_wait().as_secs(), crate::BACKGROUND_REFRESH_RETRY);
+
+        // ahead by half of a short ttl, and by `BACKGROUND_REFRESH_AHEAD` of a long one.
+        assert!((29.0..=30.0).contains(&wait_for(60)));
+        assert!((3539.0..=3540.0).contains(&wait_for(3600)));
+    }
+
+    #[cfg(not(feature = "wasm"))]
+    fn jwk(kid: &str) -> Cert {
+        Cert {
+            kid: kid.to_string(),
+            alg: "RS256".to_string(),
+            kty: "RSA".to_string(),
+            e: "AQAB".to_string(),
+            n: "n-of-".to_string() + kid,
+            crv: None,
+            x: None,
+            y: None,
+            not_after: None,
+        }
+    }
+
+    /// A path in the temporary directory, unique to the test.
+    #[cfg(not(feature = "wasm"))]
+    fn cache_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("google-oauth-{}-{}.json", std::process::id(), name))
+    }
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn persistent_cache_round_trip() {
+        let path = cache_path("round-trip");
+
+        let mut certs = Certs::new(vec![jwk("k1"), jwk("k2")]);
+        certs.set_cache_until(Instant::now() + Duration::from_secs(3600));
+        certs.save_to_file(&path).unwrap();
+
+        let loaded = Certs::load_from_file(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert!(!loaded.need_refresh());
+        assert!(loaded.cache_until().unwrap() > Instant::now() + Duration::from_secs(3590));
+
+        let kids: Vec<_> = loaded.keys().iter().map(|cert| (cert.kid.as_str(), cert.n.as_str())).collect();
+        assert_eq!(kids, [("k1", "n-of-k1"), ("k2", "n-of-k2")]);
+    }
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn persistent_cache_expired() {
+        let path = cache_path("expired");
+
+        // certs without `cache_until` are saved as expired now.
+        Certs::new(vec![jwk("k1")]).save_to_file(&path).unwrap();
+
+        let loaded = Certs::load_from_cache(&path);
+        let _ = std::fs::remove_file(&path);
+
+        let loaded = loaded.unwrap();
+        assert!(loaded.need_refresh());
+        assert_eq!(loaded.keys().len(), 1);
+    }
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn persistent_cache_corrupted() {
+        let path = cache_path("corrupted");
+
+        std::fs::write(&path, b"{\"expires_at\": 1, \"keys\": ").unwrap();
+        let loaded = Certs::load_from_cache(&path);
+        let _ = std::fs::remove_file(&path);
+
+        assert!(loaded.is_none());
+        assert!(Certs::load_from_cache(&cache_path("missing")).is_none());
+    }
+
+    /// Self-signed RSA certificate, valid from 2020 to 2120.
+    const VALID_PEM: &str = r"-----BEGIN CERTIFICATE-----
+MIIB/jCCAWegAwIBAgIUZjlpYsxTVZlrTglcRtY01PkUT0UwDQYJKoZIhvcNAQEL
+BQAwEDEOMAwGA1UEAwwFdmFsaWQwIBcNMjAwMTAxMDAwMDAwWhgPMjEyMDAxMDEw
+MDAwMDBaMBAxDjAMBgNVBAMMBXZhbGlkMIGfMA0GCSqGSIb3DQEBAQUAA4GNADCB
+iQKBgQDOr4B8lZD1TnEGXK/U+L3gSBtmerU09r5VxqJsNXxcYjWEgEyzCUflookJ
+bmp1HHQKLKeSbtV/RJGElHsTTx6o5o7Laxu5Q9XObLRxnRVqsZFUeaFT+0vGanv5
+NgNw5tchNrfhQpk7Pfvf2TdoGPXH/AMZ1g/cCCsaV/y/SNCvcQIDAQABo1MwUTAd
+BgNVHQ4EFgQUv1ikESdbMlYdHNXQtlWJkrMpkngwHwYDVR0jBBgwFoAUv1ikESdb
+MlYdHNXQtlWJkrMpkngwDwYDVR0TAQH/BAUwAwEB/zANBgkqhkiG9w0BAQsFAAOB
+gQBsrQLIiwva1hpL7mb+zhqa0Mzlf1Fb0ZllmfAc2b2KItli5c/0QtvA0NU1tavr
+hgnwzvME6GAjzW3K3VNTAe7GC7iOm4DqfrU9Op4OdzE3E4VTjhSduBXFcWFmgtYv
+pR8NnWBtrsViFkHkV95vt8LtN3emFR/r4CWI8DaqpcvOBA==
+-----END CERTIFICATE-----";
+
+    /// Self-signed RSA certificate, valid from 2000 to 2001.
+    const EXPIRED_PEM: &str = r"-----BEGIN CERTIFICATE-----
+MIICADCCAWmgAwIBAgIUJi/VCGqmzlyWJrOxE5gNPHAFEUQwDQYJKoZIhvcNAQEL
+BQAwEjEQMA4GA1UEAwwHZXhwaXJlZDAeFw0wMDAxMDEwMDAwMDBaFw0wMTAxMDEw
+MDAwMDBaMBIxEDAOBgNVBAMMB2V4cGlyZWQwgZ8wDQYJKoZIhvcNAQEBBQADgY0A
+MIGJAoGBAM6vgHyVkPVOcQZcr9T4veBIG2Z6tTT2vlXGomw1fFxiNYSATLMJR+Wi
+iQluanUcdAosp5Ju1X9EkYSUexNPHqjmjstrG7lD1c5stHGdFWqxkVR5oVP7S8Zq
+e/k2A3Dm1yE2t+FCmTs9+9/ZN2gY9cf8AxnWD9wIKxpX/L9I0K9xAgMBAAGjUzBR
+MB0GA1UdDgQWBBS/WKQRJ1syVh0c1dC2VYmSsymSeDAfBgNVHSMEGDAWgBS/WKQR
+J1syVh0c1dC2VYmSsymSeDAPBgNVHRMBAf8EBTADAQH/MA0GCSqGSIb3DQEBCwUA
+A4GBAB8LKMBksYwT29cBsL51i32m38LCtfq/5wX0gulhPeEavaedxior1cQ8pnjY
+/YXzCqZMrJRRSUR7C7O6DBQZWKqNJWeRB4RkB2zbhMosYDSttEzbtOwN7gdcpKUH
+U8xkalKunK37HSi+cruMFwX7VRL/CyCjqRu/4YfhbjVX5PJJ
+-----END CERTIFICATE-----";
+
+    /// Self-signed RSA certificate, valid from 2100 to 2101.
+    const NOT_YET_VALID_PEM: &str = r"-----BEGIN CERTIFICATE-----
+MIICAjCCAWugAwIBAgIUTJmjYg7cAjO8NuhQQnOEnUXl8GMwDQYJKoZIhvcNAQEL
+BQAwETEPMA0GA1UEAwwGZnV0dXJlMCIYDzIxMDAwMTAxMDAwMDAwWhgPMjEwMTAx
+MDEwMDAwMDBaMBExDzANBgNVBAMMBmZ1dHVyZTCBnzANBgkqhkiG9w0BAQEFAAOB
+jQAwgYkCgYEAzq+AfJWQ9U5xBlyv1Pi94EgbZnq1NPa+VcaibDV8XGI1hIBMswlH
+5aKJCW5qdRx0Ciynkm7Vf0SRhJR7E08eqOaOy2sbuUPVzmy0cZ0VarGRVHmhU/tL
+xmp7+TYDcObXITa34UKZOz3739k3aBj1x/wDGdYP3AgrGlf8v0jQr3ECAwEAAaNT
+MFEwHQYDVR0OBBYEFL9YpBEnWzJWHRzV0LZViZKzKZJ4MB8GA1UdIwQYMBaAFL9Y
+pBEnWzJWHRzV0LZViZKzKZJ4MA8GA1UdEwEB/wQFMAMBAf8wDQYJKoZIhvcNAQEL
+BQADgYEAS0vegaLIcySNOQHfP1LUZMj+VEiJyfUoiv9pLe2Blqea/i2Nz0ZL6VcQ
+vBBZazSbZGEFYwKdVITSeFD/tUqNdp1O2dizXqSoU9X4UPN7a4ITc7MSRU29Pzgh
+OGIHHil387LzLAVhxLhJPgBZh+dYoPFy9ly60wx6zMeQg+YaTFc=
+-----END CERTIFICATE-----";
+
+    /// Self-signed P-256 certificate, valid from 2020 to 2120.
+    const EC_PEM: &str = r"-----BEGIN CERTIFICATE-----
+MIIBcjCCARegAwIBAgIUdNo1Z0tuAhb75KQkpyK01MDttqAwCgYIKoZIzj0EAwIw
+DTELMAkGA1UEAwwCZWMwIBcNMjAwMTAxMDAwMDAwWhgPMjEyMDAxMDEwMDAwMDBa
+MA0xCzAJBgNVBAMMAmVjMFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEcFThQY/B
+5TyYiYnej8NNxQ8Ak3VEZNdcbohUX1dC6wOmGbkuptuGp9mT8ym0dPgdethrE8Bl
+AHSokWTFVa7be6NTMFEwHQYDVR0OBBYEFIDDhgZsOPmSEcp7ZqIUnUPlQa0IMB8G
+A1UdIwQYMBaAFIDDhgZsOPmSEcp7ZqIUnUPlQa0IMA8GA1UdEwEB/wQFMAMBAf8w
+CgYIKoZIzj0EAwIDSQAwRgIhAJcEFL6ATdtwhZ4FMqgLLBuoH/BLaqzTeTn8yLWt
+c/lqAiEAx5GadwBFaKC5EJXTWjq/CqCsdJN5GtUk1gcMM4vwKOg=
+-----END CERTIFICATE-----";
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    #[test]
+    fn pem_map_valid() {
+        let certs = Certs::from_pem_map([("k1", VALID_PEM)]).unwrap();
+
+        let cert = certs.find_cert("RS256", "k1").unwrap();
+        assert_eq!(cert.kty, "RSA");
+        assert_eq!(cert.alg, "RS256");
+        assert!(cert.not_after.unwrap() > now());
+
+        assert!(certs.find_key("RS256", "k1").is_ok());
+        assert!(certs.find_key("PS256", "k1").is_err());
+    }
+
+    #[test]
+    fn pem_out_of_validity() {
+        assert!(matches!(Cert::from_x509_pem("k1", EXPIRED_PEM), Err(crate::Error::CertInvalidError(_))));
+        assert!(matches!(Cert::from_x509_pem("k1", NOT_YET_VALID_PEM), Err(crate::Error::CertInvalidError(_))));
+
+        let certs = Certs::from_pem_map([("k1", EXPIRED_PEM), ("k2", NOT_YET_VALID_PEM)]);
+        assert!(matches!(certs, Err(crate::Error::CertInvalidError(_))));
+    }
+
+    #[test]
+    fn pem_not_rsa() {
+        assert!(matches!(Cert::from_x509_pem("k1", EC_PEM), Err(crate::Error::CertInvalidError(_))));
+        assert!(Certs::from_pem_map([("k1", EC_PEM)]).is_err());
+    }
+
+    #[test]
+    fn pem_map_mixed() {
+        let certs = Certs::from_pem_map([
+            ("expired", EXPIRED_PEM),
+            ("ec", EC_PEM),
+            ("valid", VALID_PEM),
+            ("garbage", "-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----"),
+        ]).unwrap();
+
+        let kids: Vec<_> = certs.keys().iter().map(|cert| cert.kid.as_str()).collect();
+        assert_eq!(kids, ["valid"]);
+    }
+
+    #[test]
+    fn deserialize_formats() {
+        let jwks: Certs = serde_json::from_value(serde_json::json!({
+            "keys": [{"kid": "k1", "alg": "RS256", "kty": "RSA", "e": "AQAB", "n": "n-of-k1"}],
+        })).unwrap();
+        assert_eq!(jwks.keys().len(), 1);
+        assert_eq!(jwks.keys()[0].not_after, None);
+
+        let pems: Certs = serde_json::from_value(serde_json::json!({"k1": VALID_PEM, "k2": EXPIRED_PEM})).unwrap();
+        let kids: Vec<_> = pems.keys().iter().map(|cert| cert.kid.as_str()).collect();
+        assert_eq!(kids, ["k1"]);
+
+        // a PEM map without usable certificates is rejected.
+        assert!(serde_json::from_value::<Certs>(serde_json::json!({"k2": EXPIRED_PEM})).is_err());
+    }
+
+    #[test]
+    fn deserialize_neither_format() {
+        for doc in [r#"{"error": "x"}"#, r#"{"k1": 1}"#, "{}"] {
+            let err = serde_json::from_str::<Certs>(doc).unwrap_err();
+            assert!(err.to_string().contains("neither a JWKS nor a map of X.509 certificates"), "{}: {}", doc, err);
+        }
+    }
+
+    #[test]
+    fn expired_cert_not_found() {
+        let mut certs = Certs::from_pem_map([("k1", VALID_PEM)]).unwrap();
+        certs.prepare_keys();
+        assert!(certs.find_key("RS256", "k1").is_ok());
+
+        // the decoded key is not used once `not_after` has passed.
+        certs.keys[0].not_after = Some(now() - 1);
+        assert!(matches!(certs.find_key("RS256", "k1"), Err(crate::Error::IDTokenCertNotFoundError(_))));
+        assert!(certs.find_cert("RS256", "k1").is_err());
+
+        certs.prepare_keys();
+        assert!(certs.verifying_keys.is_empty());
+    }
+}
@@ -0,0 +1,164 @@
+use serde::de::DeserializeOwned;
+use crate::{Audience, FirebasePayload, GooglePayload, IapPayload, ServiceIdTokenPayload};
+
+/// `IDTokenClaims` exposes the claims which are used when validating an `id_token`.
+///
+/// Implement it for your own payload type to deserialize extra (or custom) claims,
+/// and use it with `validate_id_token_as`.
+///
+/// Optional claims default to `None`. Note that a policy in [crate::ValidationOptions] which requires
+/// an optional claim (e.g. `nonce`) will fail if the claim is not exposed.
+pub trait IDTokenClaims: DeserializeOwned {
+    fn aud(&self) -> &Audience;
+    fn iss(&self) -> &str;
+    fn exp(&self) -> u64;
+    fn iat(&self) -> u64;
+
+    fn nbf(&self) -> Option<u64> {
+        None
+    }
+
+    fn azp(&self) -> Option<&str> {
+        None
+    }
+
+    fn nonce(&self) -> Option<&str> {
+        None
+    }
+
+    fn hd(&self) -> Option<&str> {
+        None
+    }
+
+    fn email(&self) -> Option<&str> {
+        None
+    }
+
+    fn email_verified(&self) -> Option<bool> {
+        None
+    }
+}
+
+impl IDTokenClaims for GooglePayload {
+    fn aud(&self) -> &Audience {
+        &self.aud
+    }
+
+    fn iss(&self) -> &str {
+        &self.iss
+    }
+
+    fn exp(&self) -> u64 {
+        self.exp
+    }
+
+    fn iat(&self) -> u64 {
+        self.iat
+    }
+
+    fn nbf(&self) -> Option<u64> {
+        self.nbf
+    }
+
+    fn azp(&self) -> Option<&str> {
+        self.azp.as_deref()
+    }
+
+    fn nonce(&self) -> Option<&str> {
+        self.nonce.as_deref()
+    }
+
+    fn hd(&self) -> Option<&str> {
+        self.hd.as_deref()
+    }
+
+    fn email(&self) -> Option<&str> {
+        self.email.as_deref()
+    }
+
+    fn email_verified(&self) -> Option<bool> {
+        self.email_verified
+    }
+}
+
+impl IDTokenClaims for FirebasePayload {
+    fn aud(&self) -> &Audience {
+        &self.aud
+    }
+
+    fn iss(&self) -> &str {
+        &self.iss
+    }
+
+    fn exp(&self) -> u64 {
+        self.exp
+    }
+
+    fn iat(&self) -> u64 {
+        self.iat
+    }
+
+    fn email(&self) -> Option<&str> {
+        self.email.as_deref()
+    }
+
+    fn email_verified(&self) -> Option<bool> {
+        self.email_verified
+    }
+}
+
+impl IDTokenClaims for IapPayload {
+    fn aud(&self) -> &Audience {
+        &self.aud
+    }
+
+    fn iss(&self) -> &str {
+        &self.iss
+    }
+
+    fn exp(&self) -> u64 {
+        self.exp
+    }
+
+    fn iat(&self) -> u64 {
+        self.iat
+    }
+
+    fn hd(&self) -> Option<&str> {
+        self.hd.as_deref()
+    }
+
+    fn email(&self) -> Option<&str> {
+        Some(&self.email)
+    }
+}
+
+impl IDTokenClaims for ServiceIdTokenPayload {
+    fn aud(&self) -> &Audience {
+        &self.aud
+    }
+
+    fn iss(&self) -> &str {
+        &self.iss
+    }
+
+    fn exp(&self) -> u64 {
+        self.exp
+    }
+
+    fn iat(&self) -> u64 {
+        self.iat
+    }
+
+    fn azp(&self) -> Option<&str> {
+        self.azp.as_deref()
+    }
+
+    fn email(&self) -> Option<&str> {
+        self.email.as_deref()
+    }
+
+    fn email_verified(&self) -> Option<bool> {
+        self.email_verified
+    }
+}
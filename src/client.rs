@@ -1,18 +1,24 @@
 #![allow(non_upper_case_globals)]
 
-use std::ops::Add;
-use std::sync::{Arc, RwLock};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use arc_swap::ArcSwap;
 use lazy_static::lazy_static;
-use crate::{DEFAULT_TIMEOUT, GOOGLE_OAUTH_V3_USER_INFO_API, GOOGLE_SA_CERTS_URL, GoogleAccessTokenPayload, GooglePayload, utils};
+use crate::{BACKGROUND_REFRESH_GRACE, DEFAULT_FORCED_REFRESH_INTERVAL, DEFAULT_TIMEOUT, Error, GOOGLE_OAUTH_V3_USER_INFO_API, GOOGLE_SA_CERTS_URL, GoogleAccessTokenPayload, CacheOptions, GooglePayload, IDTokenClaims, IDTokenClientIDNotFoundError, ServiceIdTokenPayload, ValidationOptions};
+use std::thread;
 use std::time::{Duration, Instant};
 use log::debug;
-use crate::certs::{Cert, Certs};
+use crate::certs::Certs;
+use crate::cert_source::{CertSource, HttpCertSource};
 use crate::jwt_parser::JwtParser;
-use crate::validate::id_token;
+use crate::validate::{id_token, policy};
+use crate::validate::key::VerifyingKey;
+use crate::utils::RefresherGuard;
 use crate::MyResult;
 
 lazy_static! {
-    static ref cb: reqwest::blocking::Client = reqwest::blocking::Client::new();
+    pub(crate) static ref cb: reqwest::blocking::Client = reqwest::blocking::Client::new();
 }
 
 /// Client is a blocking client to do verification.
@@ -20,7 +26,17 @@ lazy_static! {
 pub struct Client {
     client_ids: Vec<String>,
     timeout: Duration,
-    cached_certs: Arc<RwLock<Certs>>,
+    options: ValidationOptions,
+    certs_url: String,
+    userinfo_url: String,
+    cert_source: Option<Arc<dyn CertSource>>,
+    cached_certs: Arc<ArcSwap<Certs>>,
+    refresh_lock: Arc<Mutex<()>>,
+    background_refreshers: Arc<AtomicUsize>,
+    forced_refresh_interval: Option<Duration>,
+    last_forced_refresh: Arc<Mutex<Option<Instant>>>,
+    persistent_cache: Option<PathBuf>,
+    cache_options: CacheOptions,
 }
 
 impl Client {
@@ -44,7 +60,17 @@ impl Client {
                 .map(|c| c.as_ref().to_string())
                 .collect(),
             timeout: Duration::from_secs(DEFAULT_TIMEOUT),
+            options: ValidationOptions::default(),
+            certs_url: GOOGLE_SA_CERTS_URL.to_string(),
+            userinfo_url: GOOGLE_OAUTH_V3_USER_INFO_API.to_string(),
+            cert_source: None,
             cached_certs: Arc::default(),
+            refresh_lock: Arc::default(),
+            background_refreshers: Arc::default(),
+            forced_refresh_interval: Some(Duration::from_secs(DEFAULT_FORCED_REFRESH_INTERVAL)),
+            last_forced_refresh: Arc::default(),
+            persistent_cache: None,
+            cache_options: CacheOptions::default(),
         }
     }
 
@@ -58,52 +84,280 @@ impl Client {
         self
     }
 
+    /// Set the url to fetch certs (JWKS, or X.509 certificates in PEM) from. Default is `https://www.googleapis.com/oauth2/v3/certs`.
+    pub fn certs_url<S: ToString>(mut self, url: S) -> Self {
+        self.certs_url = url.to_string();
+
+        self
+    }
+
+    /// Set the source of certs, e.g. [crate::StaticCertSource] or [crate::FileCertSource] for offline verification.
+    ///
+    /// By default, certs are fetched from the certs url (see [Self::certs_url]) with the timeout of this client.
+    pub fn cert_source<C: CertSource + 'static>(mut self, source: C) -> Self {
+        self.cert_source = Some(Arc::new(source));
+
+        self
+    }
+
+    /// Set the minimum interval between forced refreshes.
+    ///
+    /// When the `kid` of a token is not found in the cached certs (which are not expired yet),
+    /// certs are fetched again, in case Google rotated the keys. To avoid being abused by tokens with random `kid`s,
+    /// it happens at most once per interval. Default is 60 seconds, and `None` disables forced refreshes.
+    pub fn forced_refresh_interval<D: Into<Option<Duration>>>(mut self, d: D) -> Self {
+        self.forced_refresh_interval = d.into();
+
+        self
+    }
+
+    /// Set the [CacheOptions], which controls how long fetched certs are cached.
+    pub fn cache_options(mut self, options: CacheOptions) -> Self {
+        self.cache_options = options;
+
+        self
+    }
+
+    /// Persist certs into the file `path`, so they survive restarts.
+    ///
+    /// Certs are loaded from the file now, and saved into it after each successful refresh.
+    /// If the file is expired or corrupted, certs are fetched from the cert source as usual.
+    pub fn persistent_cache<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        let path = path.into();
+
+        if let Some(certs) = Certs::load_from_cache(&path) {
+            self.cached_certs = Arc::new(ArcSwap::from_pointee(certs));
+        }
+
+        self.persistent_cache = Some(path);
+
+        self
+    }
+
+    /// Set the url of userinfo api, which is used in validating `access_token`.
+    /// Default is `https://www.googleapis.com/oauth2/v3/userinfo`.
+    pub fn userinfo_url<S: ToString>(mut self, url: S) -> Self {
+        self.userinfo_url = url.to_string();
+
+        self
+    }
+
+    /// Set the default [ValidationOptions] used by [Self::validate_id_token].
+    pub fn validation_options(mut self, options: ValidationOptions) -> Self {
+        self.options = options;
+
+        self
+    }
+
     /// Do verification with `id_token`. If success, return the user data.
     pub fn validate_id_token<S>(&self, token: S) -> MyResult<GooglePayload>
         where S: AsRef<str>
+    {
+        self.validate_id_token_with_options(token, &self.options)
+    }
+
+    /// Do verification with `id_token`, using `options` instead of the default [ValidationOptions] of this client.
+    pub fn validate_id_token_with_options<S>(&self, token: S, options: &ValidationOptions) -> MyResult<GooglePayload>
+        where S: AsRef<str>
+    {
+        self.validate_id_token_as_with_options(token, options)
+    }
+
+    /// Do verification with `id_token`, and deserialize the payload as `T`.
+    ///
+    /// It is useful when you need extra (or custom) claims, which are not listed in [GooglePayload].
+    pub fn validate_id_token_as<T, S>(&self, token: S) -> MyResult<T>
+        where
+            T: IDTokenClaims,
+            S: AsRef<str>,
+    {
+        self.validate_id_token_as_with_options(token, &self.options)
+    }
+
+    /// Same as [Self::validate_id_token_as], but using `options` instead of the default [ValidationOptions] of this client.
+    pub fn validate_id_token_as_with_options<T, S>(&self, token: S, options: &ValidationOptions) -> MyResult<T>
+        where
+            T: IDTokenClaims,
+            S: AsRef<str>,
     {
         let token = token.as_ref();
 
-        let parser: JwtParser<GooglePayload> = JwtParser::parse(token)?;
+        let parser: JwtParser<T> = JwtParser::parse(token)?;
 
-        id_token::validate_info(&self.client_ids, &parser)?;
+        id_token::validate_info(&self.client_ids, &parser, options)?;
 
-        let cert = self.get_cert(parser.header.alg.as_str(), parser.header.kid.as_str())?;
+        let key = self.get_key(parser.header.alg.as_str(), parser.header.kid.as_str())?;
 
-        id_token::do_validate(&cert, &parser)?;
+        id_token::do_validate(&key, &parser)?;
 
         Ok(parser.payload)
     }
 
-    fn get_cert(&self, alg: &str, kid: &str) -> MyResult<Cert> {
+    /// Do verification with `id_token`, and require its `nonce` claim to be equal to `nonce`.
+    pub fn validate_id_token_with_nonce<S, N>(&self, token: S, nonce: N) -> MyResult<GooglePayload>
+        where
+            S: AsRef<str>,
+            N: ToString,
+    {
+        let options = self.options.clone().nonce(nonce);
+
+        self.validate_id_token_with_options(token, &options)
+    }
+
+    /// Do verification with a Google-signed `id_token` of a service account, e.g. when Cloud Run, Cloud Functions,
+    /// Cloud Scheduler or Cloud Tasks call this service. The client id of this client is the audience of the token,
+    /// which is usually the url of this service.
+    ///
+    /// The `email` of the token must be listed in [ValidationOptions::service_accounts], and be verified.
+    /// Note that any Google account can get a token for any audience, so every token is rejected if no service account is allowed.
+    /// [ValidationOptions::hosted_domains] and [ValidationOptions::reject_consumer_accounts] are not checked.
+    pub fn validate_service_id_token<S>(&self, token: S) -> MyResult<ServiceIdTokenPayload>
+        where S: AsRef<str>
+    {
+        self.validate_service_id_token_with_options(token, &self.options)
+    }
+
+    /// Same as [Self::validate_service_id_token], but using `options` instead of the default [ValidationOptions] of this client.
+    pub fn validate_service_id_token_with_options<S>(&self, token: S, options: &ValidationOptions) -> MyResult<ServiceIdTokenPayload>
+        where S: AsRef<str>
+    {
+        // service accounts have no `hd`, so the hosted domain policy would reject every token.
+        let options = &options.without_hosted_domains();
+        let payload: ServiceIdTokenPayload = self.validate_id_token_as_with_options(token, options)?;
+
+        // the audience is never skipped here, even if this client has no client id.
+        if self.client_ids.is_empty() {
+            Err(IDTokenClientIDNotFoundError::new(&payload.aud, Vec::<String>::new()))?
+        }
+
+        policy::validate_service_account(payload.email.as_deref(), payload.email_verified, options)?;
+
+        Ok(payload)
+    }
+
+    fn get_key(&self, alg: &str, kid: &str) -> MyResult<Arc<VerifyingKey>> {
+        let mut forced = false;
+
         {
-            let cached_certs = self.cached_certs.read().unwrap();
-            if !cached_certs.need_refresh() {
+            let cached_certs = self.cached_certs.load();
+            let usable = !cached_certs.need_refresh()
+                // stale-while-revalidate: a running background refresher will replace them soon,
+                // but certs expired for too long (e.g. the refresher keeps failing) are fetched here.
+                || (self.background_refreshers.load(Ordering::Relaxed) > 0
+                    && cached_certs.is_stale_within(Duration::from_secs(BACKGROUND_REFRESH_GRACE)));
+
+            if usable {
                 debug!("certs: use cache");
-                return cached_certs.find_cert(alg, kid);
+
+                match cached_certs.find_key(alg, kid) {
+                    Err(Error::IDTokenCertNotFoundError(_)) if self.allow_forced_refresh() => {
+                        debug!("certs: kid {} not found in cache, force refresh", kid);
+                        forced = true;
+                    }
+                    result => return result,
+                }
+            }
+        }
+
+        // single-flight: only one fetch is in progress, other callers wait for its result.
+        let _refreshing = self.refresh_lock.lock().unwrap_or_else(PoisonError::into_inner);
+
+        {
+            // double-check: certs may be refreshed by another caller while we were waiting.
+            let cached_certs = self.cached_certs.load();
+            if !cached_certs.need_refresh() {
+                match cached_certs.find_key(alg, kid) {
+                    // a forced refresh is still needed if the kid is missing.
+                    Err(Error::IDTokenCertNotFoundError(_)) if forced => {}
+                    result => {
+                        debug!("certs: refreshed by another caller");
+                        return result;
+                    }
+                }
             }
         }
 
         debug!("certs: try to fetch new certs");
 
-        let mut cached_certs = self.cached_certs.write().unwrap();
+        // fetch while holding only the single-flight lock: validations keep reading the previous certs,
+        // until the new ones are stored.
+        let certs = fetch(&*self.source(), &self.cached_certs, self.cache_options)?;
 
-        // we need to refresh certs here...
-        let resp = cb.get(GOOGLE_SA_CERTS_URL)
-            .timeout(self.timeout)
-            .send()?;
+        certs.save_to_cache(self.persistent_cache.as_deref());
 
-        // parse the response header `max-age`.
-        let max_age = utils::parse_max_age_from_resp(&resp);
+        let key = certs.find_key(alg, kid);
+        self.cached_certs.store(Arc::new(certs));
 
-        let info = resp.bytes()?;
-        *cached_certs = serde_json::from_slice(&info)?;
+        key
+    }
 
-        cached_certs.set_cache_until(
-            Instant::now().add(Duration::from_secs(max_age))
-        );
+    fn allow_forced_refresh(&self) -> bool {
+        let Some(interval) = self.forced_refresh_interval else {
+            return false;
+        };
+
+        let mut last = self.last_forced_refresh.lock().unwrap_or_else(PoisonError::into_inner);
+        if last.is_some_and(|last| last.elapsed() < interval) {
+            return false;
+        }
+
+        *last = Some(Instant::now());
+        true
+    }
+
+    fn source(&self) -> Arc<dyn CertSource> {
+        match &self.cert_source {
+            Some(source) => source.clone(),
+            None => Arc::new(HttpCertSource::new(&self.certs_url).timeout(self.timeout)),
+        }
+    }
+
+    /// Fetch certs from the cert source now, and replace the cached certs.
+    ///
+    /// The cached certs are still used by other validations while fetching.
+    pub fn refresh_certs(&self) -> MyResult<()> {
+        refresh(&*self.source(), &self.cached_certs, &self.refresh_lock, self.persistent_cache.as_deref(), self.cache_options)
+    }
+
+    /// Spawn a thread, which refreshes the cached certs shortly before they expire.
+    ///
+    /// Once the refresher is spawned, validations never wait for fetching certs (except the first time),
+    /// but keep using the previous certs until the new ones arrive (for at most 5 minutes after they expire).
+    /// If fetching fails, it will be retried later.
+    ///
+    /// The thread exits on its next wake-up after this client (and all its clones) are dropped,
+    /// i.e. shortly before the cached certs expire, or within 10 seconds after a failure.
+    pub fn spawn_background_refresh(&self) -> thread::JoinHandle<()> {
+        let alive = RefresherGuard::new(self.background_refreshers.clone());
+
+        let source = self.source();
+        let cached_certs = Arc::downgrade(&self.cached_certs);
+        let refresh_lock = self.refresh_lock.clone();
+        let persistent_cache = self.persistent_cache.clone();
+        let cache_options = self.cache_options;
 
-        cached_certs.find_cert(alg, kid)
+        thread::spawn(move || {
+            // dropped when the refresher stops for any reason.
+            let _alive = alive;
+            let mut wait = Duration::ZERO;
+
+            loop {
+                thread::sleep(wait);
+
+                let Some(cached_certs) = cached_certs.upgrade() else {
+                    debug!("certs: client dropped, stop background refresh");
+                    return;
+                };
+
+                wait = match refresh(&*source, &cached_certs, &refresh_lock, persistent_cache.as_deref(), cache_options) {
+                    Ok(_) => cached_certs.load().refresh_ahead_wait(),
+                    Err(e) => {
+                        log::warn!("certs: background refresh failed: {}", e);
+                        Duration::from_secs(crate::BACKGROUND_REFRESH_RETRY)
+                    }
+                };
+            }
+        })
     }
 
     /// Try to validate access token. If success, return the user info.
@@ -112,13 +366,182 @@ impl Client {
     {
         let token = token.as_ref();
 
-        let info = cb.get(format!("{}?access_token={}", GOOGLE_OAUTH_V3_USER_INFO_API, token))
+        let info = cb.get(format!("{}?access_token={}", self.userinfo_url, token))
             .timeout(self.timeout)
             .send()?
             .bytes()?;
 
-        let payload = serde_json::from_slice(&info)?;
+        let payload: GoogleAccessTokenPayload = serde_json::from_slice(&info)?;
+        policy::validate_hosted_domain(payload.hd.as_deref(), &self.options)?;
 
         Ok(payload)
     }
 }
+
+/// Fetch certs under the single-flight `refresh_lock`, then store them at once.
+/// Validations with usable certs do not wait for the lock, so they keep reading the previous certs while fetching.
+fn refresh(source: &dyn CertSource, cached_certs: &ArcSwap<Certs>, refresh_lock: &Mutex<()>, persistent_cache: Option<&Path>, cache_options: CacheOptions) -> MyResult<()> {
+    let _refreshing = refresh_lock.lock().unwrap_or_else(PoisonError::into_inner);
+
+    debug!("certs: refresh");
+
+    let certs = fetch(source, cached_certs, cache_options)?;
+    certs.save_to_cache(persistent_cache);
+    cached_certs.store(Arc::new(certs));
+
+    Ok(())
+}
+
+/// Fetch certs from `source`, revalidating the cached certs if there are any, and apply `cache_options`.
+fn fetch(source: &dyn CertSource, cached_certs: &ArcSwap<Certs>, cache_options: CacheOptions) -> MyResult<Certs> {
+    let cached = cached_certs.load_full();
+
+    let mut certs = if cached.keys().is_empty() {
+        source.fetch_certs()?
+    } else {
+        source.revalidate_certs(&cached)?
+    };
+
+    cache_options.apply(&mut certs);
+    certs.prepare_keys();
+
+    Ok(certs)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use base64::Engine;
+    use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+    use p256::ecdsa::{Signature, SigningKey};
+    use p256::ecdsa::signature::Signer;
+    use crate::{Cert, StaticCertSource};
+    use super::*;
+
+    /// A source of empty certs, counting how many times certs are fetched. Each fetch takes `delay`.
+    #[derive(Debug, Default, Clone)]
+    struct CountingSource {
+        fetches: Arc<AtomicUsize>,
+        delay: Duration,
+    }
+
+    impl CountingSource {
+        fn fetches(&self) -> usize {
+            self.fetches.load(Ordering::SeqCst)
+        }
+    }
+
+    impl CertSource for CountingSource {
+        fn fetch_certs(&self) -> MyResult<Certs> {
+            self.fetches.fetch_add(1, Ordering::SeqCst);
+            thread::sleep(self.delay);
+
+            let mut certs = Certs::default();
+            certs.set_cache_until(Instant::now() + Duration::from_secs(3600));
+
+            Ok(certs)
+        }
+    }
+
+    #[test]
+    fn forced_refresh_rate_limited() {
+        let source = CountingSource::default();
+        let client = Client::new("").cert_source(source.clone());
+
+        for i in 0..5 {
+            let result = client.get_key("RS256", &format!("unknown-{}", i));
+            assert!(matches!(result, Err(Error::IDTokenCertNotFoundError(_))));
+        }
+
+        // the first fetch, and only one forced refresh within the interval.
+        assert_eq!(source.fetches(), 2);
+    }
+
+    #[test]
+    fn forced_refresh_disabled() {
+        let source = CountingSource::default();
+        let client = Client::new("")
+            .cert_source(source.clone())
+            .forced_refresh_interval(None);
+
+        for i in 0..5 {
+            let result = client.get_key("RS256", &format!("unknown-{}", i));
+            assert!(matches!(result, Err(Error::IDTokenCertNotFoundError(_))));
+        }
+
+        assert_eq!(source.fetches(), 1);
+    }
+
+    #[test]
+    fn single_flight() {
+        let source = CountingSource { delay: Duration::from_millis(100), ..Default::default() };
+        // no forced refresh, so callers after the fetch do not fetch again for the missing kid.
+        let client = Client::new("")
+            .cert_source(source.clone())
+            .forced_refresh_interval(None);
+
+        let callers: Vec<_> = (0..8)
+            .map(|_| {
+                let client = client.clone();
+                thread::spawn(move || client.get_key("RS256", "k1"))
+            })
+            .collect();
+
+        for caller in callers {
+            assert!(matches!(caller.join().unwrap(), Err(Error::IDTokenCertNotFoundError(_))));
+        }
+
+        assert_eq!(source.fetches(), 1);
+    }
+
+    /// A Google-signed (ES256, with a random key) service `id_token` for `aud`, and the certs to verify it.
+    fn service_id_token(aud: &str) -> (String, Certs) {
+        let key = SigningKey::random(&mut rsa::rand_core::OsRng);
+        let point = key.verifying_key().to_encoded_point(false);
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+        let msg = format!(
+            "{}.{}",
+            BASE64_URL_SAFE_NO_PAD.encode(serde_json::json!({"alg": "ES256", "kid": "k1"}).to_string()),
+            BASE64_URL_SAFE_NO_PAD.encode(serde_json::json!({
+                "aud": aud,
+                "iss": "https://accounts.google.com",
+                "sub": "1234",
+                "email": "caller@my-project.iam.gserviceaccount.com",
+                "email_verified": true,
+                "iat": now,
+                "exp": now + 3600,
+            }).to_string()),
+        );
+        let sig: Signature = key.sign(msg.as_bytes());
+
+        let certs = Certs::new(vec![Cert {
+            kid: "k1".to_string(),
+            alg: "ES256".to_string(),
+            kty: "EC".to_string(),
+            e: String::new(),
+            n: String::new(),
+            crv: Some("P-256".to_string()),
+            x: point.x().map(|x| BASE64_URL_SAFE_NO_PAD.encode(x)),
+            y: point.y().map(|y| BASE64_URL_SAFE_NO_PAD.encode(y)),
+            not_after: None,
+        }]);
+
+        (format!("{}.{}", msg, BASE64_URL_SAFE_NO_PAD.encode(sig.to_bytes())), certs)
+    }
+
+    #[test]
+    fn service_id_token_without_hosted_domain() {
+        let (token, certs) = service_id_token("https://my-service.run.app");
+        let client = Client::new("https://my-service.run.app")
+            .cert_source(StaticCertSource::new(certs))
+            .validation_options(ValidationOptions::new()
+                .service_accounts(["caller@my-project.iam.gserviceaccount.com"])
+                .hosted_domains(["example.com"])
+                .reject_consumer_accounts(true));
+
+        // service accounts have no `hd`, so the hosted domain policy applies to user tokens only.
+        assert!(matches!(client.validate_id_token(&token), Err(Error::HostedDomainNotAllowedError(_))));
+        assert!(client.validate_service_id_token(&token).is_ok());
+    }
+}
@@ -1,5 +1,4 @@
 use std::fmt::{Debug, Display, Formatter};
-use crate::GOOGLE_ISS;
 #[cfg(feature = "wasm")]
 use web_time::SystemTimeError;
 #[cfg(not(feature = "wasm"))]
@@ -19,7 +18,7 @@ pub enum Error {
     IDTokenExpiredError(IDTokenExpiredError),
     /// Any [SystemTimeError]
     SystemTimeError(SystemTimeError),
-    /// Error when id_token has an issuer which not listed in [GOOGLE_ISS]
+    /// Error when id_token has an issuer which not listed in [crate::ValidationOptions::issuers]
     GoogleIssuerNotMatchError(GoogleIssuerNotMatchError),
     /// Error when id_token has a client_id which not listed when client was created.
     IDTokenClientIDNotFoundError(IDTokenClientIDNotFoundError),
@@ -33,6 +32,36 @@ pub enum Error {
     IDTokenCertNotFoundError(IDTokenCertNotFoundError),
     /// Any [reqwest::Error]
     ReqwestError(reqwest::Error),
+    /// Any [p256::ecdsa::Error]
+    ES256SignatureError(p256::ecdsa::Error),
+    /// Error when a cert from google server cannot be turned into a public key
+    CertInvalidError(CertInvalidError),
+    /// Error when id_token is not valid yet (`nbf` is later than now)
+    IDTokenNotYetValidError(IDTokenNotYetValidError),
+    /// Error when id_token is issued in the future (`iat` is later than now)
+    IDTokenIssuedInFutureError(IDTokenIssuedInFutureError),
+    /// Error when id_token is issued too long ago, see [crate::ValidationOptions::max_age]
+    IDTokenTooOldError(IDTokenTooOldError),
+    /// Error when id_token has no nonce, or a nonce different from the expected one
+    IDTokenNonceNotMatchError(IDTokenNonceNotMatchError),
+    /// Error when the hosted domain (`hd`) of an account is not allowed
+    HostedDomainNotAllowedError(HostedDomainNotAllowedError),
+    /// Error when the email of an account is missing or not verified
+    EmailNotVerifiedError(EmailNotVerifiedError),
+    /// Error when id_token has multiple audiences, but its authorized party (`azp`) is missing or not listed when client was created.
+    IDTokenAzpNotMatchError(IDTokenAzpNotMatchError),
+    /// Any [std::io::Error]
+    IoError(std::io::Error),
+    /// Error when the user of a Firebase id_token is authenticated in the future (`auth_time` is later than now)
+    IDTokenAuthTimeInFutureError(IDTokenAuthTimeInFutureError),
+    /// Error when id_token has an empty (or too long) subject (`sub`)
+    IDTokenSubjectInvalidError(IDTokenSubjectInvalidError),
+    /// Error when the email of a service account is not listed in [crate::ValidationOptions::service_accounts]
+    ServiceAccountNotAllowedError(ServiceAccountNotAllowedError),
+    /// Error when the `Authorization` header does not carry a bearer token
+    AuthorizationHeaderInvalidError(AuthorizationHeaderInvalidError),
+    /// Error when certs are neither a JWKS nor a map of X.509 certificates in PEM
+    CertsFormatInvalidError(CertsFormatInvalidError),
 }
 
 impl Display for Error {
@@ -50,6 +79,21 @@ impl Display for Error {
             Self::HashAlgorithmUnimplementedError(e) => Display::fmt(&e, f),
             Self::IDTokenCertNotFoundError(e) => Display::fmt(&e, f),
             Self::ReqwestError(e) => Display::fmt(&e, f),
+            Self::ES256SignatureError(e) => Display::fmt(&e, f),
+            Self::CertInvalidError(e) => Display::fmt(&e, f),
+            Self::IDTokenNotYetValidError(e) => Display::fmt(&e, f),
+            Self::IDTokenIssuedInFutureError(e) => Display::fmt(&e, f),
+            Self::IDTokenTooOldError(e) => Display::fmt(&e, f),
+            Self::IDTokenNonceNotMatchError(e) => Display::fmt(&e, f),
+            Self::HostedDomainNotAllowedError(e) => Display::fmt(&e, f),
+            Self::EmailNotVerifiedError(e) => Display::fmt(&e, f),
+            Self::IDTokenAzpNotMatchError(e) => Display::fmt(&e, f),
+            Self::IoError(e) => Display::fmt(&e, f),
+            Self::IDTokenAuthTimeInFutureError(e) => Display::fmt(&e, f),
+            Self::IDTokenSubjectInvalidError(e) => Display::fmt(&e, f),
+            Self::ServiceAccountNotAllowedError(e) => Display::fmt(&e, f),
+            Self::AuthorizationHeaderInvalidError(e) => Display::fmt(&e, f),
+            Self::CertsFormatInvalidError(e) => Display::fmt(&e, f),
         }
     }
 }
@@ -136,15 +180,19 @@ impl From<SystemTimeError> for Error {
 #[derive(Debug)]
 pub struct GoogleIssuerNotMatchError {
     pub get: String,
-    pub expected: [&'static str; 2],
+    pub expected: Vec<String>,
 }
 
 impl GoogleIssuerNotMatchError {
-    #[inline]
-    pub fn new<S: ToString>(get: S) -> Self {
+    pub fn new<S, T, V>(get: S, expected: T) -> Self
+        where
+            S: ToString,
+            T: AsRef<[V]>,
+            V: AsRef<str>
+    {
         Self {
             get: get.to_string(),
-            expected: GOOGLE_ISS,
+            expected: expected.as_ref().iter().map(|e| e.as_ref().to_string()).collect(),
         }
     }
 }
@@ -276,3 +324,411 @@ impl From<reqwest::Error> for Error {
         Self::ReqwestError(err)
     }
 }
+
+#[derive(Debug)]
+pub struct CertInvalidError {
+    pub kid: String,
+    pub reason: String,
+}
+
+impl CertInvalidError {
+    #[inline]
+    pub fn new<S: ToString, R: ToString>(kid: S, reason: R) -> Self {
+        Self {
+            kid: kid.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+impl Display for CertInvalidError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cert kid={} is invalid: {}", &self.kid, &self.reason)
+    }
+}
+
+impl std::error::Error for CertInvalidError {}
+
+impl From<CertInvalidError> for Error {
+    #[inline]
+    fn from(err: CertInvalidError) -> Self {
+        Self::CertInvalidError(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct IDTokenNotYetValidError {
+    pub now: u64,
+    pub nbf: u64,
+}
+
+impl IDTokenNotYetValidError {
+    #[inline]
+    pub fn new(now: u64, nbf: u64) -> Self {
+        Self { now, nbf }
+    }
+}
+
+impl Display for IDTokenNotYetValidError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "token not valid yet, {} < {}", self.now, self.nbf)
+    }
+}
+
+impl std::error::Error for IDTokenNotYetValidError {}
+
+impl From<IDTokenNotYetValidError> for Error {
+    #[inline]
+    fn from(err: IDTokenNotYetValidError) -> Self {
+        Self::IDTokenNotYetValidError(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct IDTokenIssuedInFutureError {
+    pub now: u64,
+    pub iat: u64,
+}
+
+impl IDTokenIssuedInFutureError {
+    #[inline]
+    pub fn new(now: u64, iat: u64) -> Self {
+        Self { now, iat }
+    }
+}
+
+impl Display for IDTokenIssuedInFutureError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "token issued in the future, {} < {}", self.now, self.iat)
+    }
+}
+
+impl std::error::Error for IDTokenIssuedInFutureError {}
+
+impl From<IDTokenIssuedInFutureError> for Error {
+    #[inline]
+    fn from(err: IDTokenIssuedInFutureError) -> Self {
+        Self::IDTokenIssuedInFutureError(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct IDTokenTooOldError {
+    pub now: u64,
+    pub iat: u64,
+    pub max_age: u64,
+}
+
+impl IDTokenTooOldError {
+    #[inline]
+    pub fn new(now: u64, iat: u64, max_age: u64) -> Self {
+        Self { now, iat, max_age }
+    }
+}
+
+impl Display for IDTokenTooOldError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "token too old, issued at {}, now is {}, but max age is {}s", self.iat, self.now, self.max_age)
+    }
+}
+
+impl std::error::Error for IDTokenTooOldError {}
+
+impl From<IDTokenTooOldError> for Error {
+    #[inline]
+    fn from(err: IDTokenTooOldError) -> Self {
+        Self::IDTokenTooOldError(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct IDTokenNonceNotMatchError {
+    pub get: Option<String>,
+}
+
+impl IDTokenNonceNotMatchError {
+    #[inline]
+    pub fn new(get: Option<String>) -> Self {
+        Self { get }
+    }
+}
+
+impl Display for IDTokenNonceNotMatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.get {
+            Some(get) => write!(f, "id_token nonce not match, get {}", get),
+            None => write!(f, "id_token nonce is missing"),
+        }
+    }
+}
+
+impl std::error::Error for IDTokenNonceNotMatchError {}
+
+impl From<IDTokenNonceNotMatchError> for Error {
+    #[inline]
+    fn from(err: IDTokenNonceNotMatchError) -> Self {
+        Self::IDTokenNonceNotMatchError(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct HostedDomainNotAllowedError {
+    /// The hosted domain of the account. `None` for consumer accounts.
+    pub get: Option<String>,
+    pub expected: Vec<String>,
+}
+
+impl HostedDomainNotAllowedError {
+    pub fn new<T, V>(get: Option<&str>, expected: T) -> Self
+        where
+            T: AsRef<[V]>,
+            V: AsRef<str>
+    {
+        Self {
+            get: get.map(|hd| hd.to_string()),
+            expected: expected.as_ref().iter().map(|e| e.as_ref().to_string()).collect(),
+        }
+    }
+}
+
+impl Display for HostedDomainNotAllowedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.get {
+            Some(hd) => write!(f, "hosted domain {} is not allowed, expected one of {:?}", hd, &self.expected),
+            None => write!(f, "consumer account (no hosted domain) is not allowed"),
+        }
+    }
+}
+
+impl std::error::Error for HostedDomainNotAllowedError {}
+
+impl From<HostedDomainNotAllowedError> for Error {
+    #[inline]
+    fn from(err: HostedDomainNotAllowedError) -> Self {
+        Self::HostedDomainNotAllowedError(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct EmailNotVerifiedError {
+    /// The email of the account. `None` if the `email` claim is missing.
+    pub email: Option<String>,
+}
+
+impl EmailNotVerifiedError {
+    #[inline]
+    pub fn new(email: Option<&str>) -> Self {
+        Self { email: email.map(|e| e.to_string()) }
+    }
+}
+
+impl Display for EmailNotVerifiedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.email {
+            Some(email) => write!(f, "email {} is not verified", email),
+            None => write!(f, "email is missing"),
+        }
+    }
+}
+
+impl std::error::Error for EmailNotVerifiedError {}
+
+impl From<EmailNotVerifiedError> for Error {
+    #[inline]
+    fn from(err: EmailNotVerifiedError) -> Self {
+        Self::EmailNotVerifiedError(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct IDTokenAzpNotMatchError {
+    /// The `azp` of the id_token. `None` if it is missing.
+    pub get: Option<String>,
+    pub expected: Vec<String>,
+}
+
+impl IDTokenAzpNotMatchError {
+    pub fn new<T, V>(get: Option<&str>, expected: T) -> Self
+        where
+            T: AsRef<[V]>,
+            V: AsRef<str>
+    {
+        Self {
+            get: get.map(|azp| azp.to_string()),
+            expected: expected.as_ref().iter().map(|e| e.as_ref().to_string()).collect(),
+        }
+    }
+}
+
+impl Display for IDTokenAzpNotMatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.get {
+            Some(azp) => write!(f, "id_token azp not match, get {}, but expected one of {:?}", azp, &self.expected),
+            None => write!(f, "id_token has multiple audiences, but azp is missing"),
+        }
+    }
+}
+
+impl std::error::Error for IDTokenAzpNotMatchError {}
+
+impl From<IDTokenAzpNotMatchError> for Error {
+    #[inline]
+    fn from(err: IDTokenAzpNotMatchError) -> Self {
+        Self::IDTokenAzpNotMatchError(err)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    #[inline]
+    fn from(err: std::io::Error) -> Self {
+        Self::IoError(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct IDTokenAuthTimeInFutureError {
+    pub now: u64,
+    pub auth_time: u64,
+}
+
+impl IDTokenAuthTimeInFutureError {
+    #[inline]
+    pub fn new(now: u64, auth_time: u64) -> Self {
+        Self { now, auth_time }
+    }
+}
+
+impl Display for IDTokenAuthTimeInFutureError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "user authenticated in the future, {} < {}", self.now, self.auth_time)
+    }
+}
+
+impl std::error::Error for IDTokenAuthTimeInFutureError {}
+
+impl From<IDTokenAuthTimeInFutureError> for Error {
+    #[inline]
+    fn from(err: IDTokenAuthTimeInFutureError) -> Self {
+        Self::IDTokenAuthTimeInFutureError(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct IDTokenSubjectInvalidError {
+    pub get: String,
+}
+
+impl IDTokenSubjectInvalidError {
+    #[inline]
+    pub fn new<S: ToString>(get: S) -> Self {
+        Self { get: get.to_string() }
+    }
+}
+
+impl Display for IDTokenSubjectInvalidError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.get.is_empty() {
+            write!(f, "id_token sub is empty")
+        } else {
+            write!(f, "id_token sub is too long, get {} characters", self.get.chars().count())
+        }
+    }
+}
+
+impl std::error::Error for IDTokenSubjectInvalidError {}
+
+impl From<IDTokenSubjectInvalidError> for Error {
+    #[inline]
+    fn from(err: IDTokenSubjectInvalidError) -> Self {
+        Self::IDTokenSubjectInvalidError(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct ServiceAccountNotAllowedError {
+    pub get: String,
+    pub expected: Vec<String>,
+}
+
+impl ServiceAccountNotAllowedError {
+    pub fn new<T, V>(get: &str, expected: T) -> Self
+        where
+            T: AsRef<[V]>,
+            V: AsRef<str>
+    {
+        Self {
+            get: get.to_string(),
+            expected: expected.as_ref().iter().map(|e| e.as_ref().to_string()).collect(),
+        }
+    }
+}
+
+impl Display for ServiceAccountNotAllowedError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "service account {} is not allowed, expected one of {:?}", self.get, &self.expected)
+    }
+}
+
+impl std::error::Error for ServiceAccountNotAllowedError {}
+
+impl From<ServiceAccountNotAllowedError> for Error {
+    #[inline]
+    fn from(err: ServiceAccountNotAllowedError) -> Self {
+        Self::ServiceAccountNotAllowedError(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct AuthorizationHeaderInvalidError {
+    /// Why the header is invalid. The header itself is not kept, since it may carry a credential.
+    pub reason: String,
+}
+
+impl AuthorizationHeaderInvalidError {
+    #[inline]
+    pub fn new<R: ToString>(reason: R) -> Self {
+        Self { reason: reason.to_string() }
+    }
+}
+
+impl Display for AuthorizationHeaderInvalidError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid authorization header: {}", self.reason)
+    }
+}
+
+impl std::error::Error for AuthorizationHeaderInvalidError {}
+
+impl From<AuthorizationHeaderInvalidError> for Error {
+    #[inline]
+    fn from(err: AuthorizationHeaderInvalidError) -> Self {
+        Self::AuthorizationHeaderInvalidError(err)
+    }
+}
+
+#[derive(Debug)]
+pub struct CertsFormatInvalidError {
+    pub reason: String,
+}
+
+impl CertsFormatInvalidError {
+    #[inline]
+    pub fn new<R: ToString>(reason: R) -> Self {
+        Self { reason: reason.to_string() }
+    }
+}
+
+impl Display for CertsFormatInvalidError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "certs are neither a JWKS nor a map of X.509 certificates in PEM: {}", self.reason)
+    }
+}
+
+impl std::error::Error for CertsFormatInvalidError {}
+
+impl From<CertsFormatInvalidError> for Error {
+    #[inline]
+    fn from(err: CertsFormatInvalidError) -> Self {
+        Self::CertsFormatInvalidError(err)
+    }
+}
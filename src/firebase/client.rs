@@ -0,0 +1,83 @@
+use crate::{Client, FirebasePayload, MyResult, ValidationOptions};
+use crate::firebase::{FIREBASE_CERTS_URL, firebase_options};
+use crate::validate::firebase;
+
+/// FirebaseClient is a blocking client to verify `id_token`s from Firebase Authentication.
+///
+/// Certs are fetched and cached by a [Client], see [Self::with_client].
+#[derive(Debug, Clone)]
+pub struct FirebaseClient {
+    project_id: String,
+    client: Client,
+    options: ValidationOptions,
+}
+
+impl FirebaseClient {
+    /// Create a new blocking client for the Firebase project `project_id`.
+    pub fn new<S: ToString>(project_id: S) -> Self {
+        let project_id = project_id.to_string();
+
+        Self::with_client(Client::new(&project_id).certs_url(FIREBASE_CERTS_URL), project_id)
+    }
+
+    /// Create a new blocking client for the Firebase project `project_id`, which fetches and caches certs with `client`,
+    /// e.g. to set [Client::cache_options] or [Client::persistent_cache].
+    ///
+    /// `client` should fetch certs from [FIREBASE_CERTS_URL], and have no client id (or `project_id`).
+    /// Its default [ValidationOptions] are not used.
+    pub fn with_client<S: ToString>(client: Client, project_id: S) -> Self {
+        let project_id = project_id.to_string();
+
+        Self {
+            client,
+            options: firebase_options(&project_id, ValidationOptions::default()),
+            project_id,
+        }
+    }
+
+    #[inline]
+    pub fn project_id(&self) -> &str {
+        &self.project_id
+    }
+
+    /// The client which fetches and caches certs, e.g. for [Client::refresh_certs] or `spawn_background_refresh`.
+    #[inline]
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Set the default [ValidationOptions] used by [Self::validate_id_token].
+    ///
+    /// [ValidationOptions::issuers] is ignored, since the issuer is determined by the project id.
+    /// [ValidationOptions::reject_future_iat] is always enabled.
+    pub fn validation_options(mut self, options: ValidationOptions) -> Self {
+        self.options = firebase_options(&self.project_id, options);
+
+        self
+    }
+
+    /// Do verification with a Firebase `id_token`. If success, return the user data.
+    pub fn validate_id_token<S>(&self, token: S) -> MyResult<FirebasePayload>
+        where S: AsRef<str>
+    {
+        self.validate(token, &self.options)
+    }
+
+    /// Do verification with a Firebase `id_token`, using `options` instead of the default [ValidationOptions] of this client.
+    pub fn validate_id_token_with_options<S>(&self, token: S, options: &ValidationOptions) -> MyResult<FirebasePayload>
+        where S: AsRef<str>
+    {
+        let options = firebase_options(&self.project_id, options.clone());
+
+        self.validate(token, &options)
+    }
+
+    fn validate<S>(&self, token: S, options: &ValidationOptions) -> MyResult<FirebasePayload>
+        where S: AsRef<str>
+    {
+        let payload: FirebasePayload = self.client.validate_id_token_as_with_options(token, options)?;
+        firebase::validate_firebase_info(&self.project_id, &payload, options)?;
+
+        Ok(payload)
+    }
+}
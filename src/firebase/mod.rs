@@ -0,0 +1,20 @@
+#[cfg(feature = "blocking")]
+mod client;
+mod async_client;
+
+#[cfg(feature = "blocking")]
+pub use client::*;
+pub use async_client::*;
+
+use crate::{FIREBASE_ISS_PREFIX, ValidationOptions};
+
+/// The url of the X.509 certificates which sign Firebase `id_token`s.
+pub const FIREBASE_CERTS_URL: &str = "https://www.googleapis.com/robot/v1/metadata/x509/securetoken@system.gserviceaccount.com";
+
+/// Firebase `id_token`s are always issued by `https://securetoken.google.com/<project_id>`,
+/// and must not be issued in the future.
+pub(crate) fn firebase_options(project_id: &str, options: ValidationOptions) -> ValidationOptions {
+    options
+        .issuers([format!("{}{}", FIREBASE_ISS_PREFIX, project_id)])
+        .reject_future_iat(true)
+}
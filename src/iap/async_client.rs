@@ -0,0 +1,85 @@
+use crate::{AsyncClient, IapPayload, MyResult, ValidationOptions};
+use crate::iap::{IAP_CERTS_URL, iap_options};
+use crate::validate::policy;
+
+/// AsyncIapVerifier is an async client to verify the signed header (`x-goog-iap-jwt-assertion`)
+/// from Identity-Aware Proxy.
+///
+/// The header is signed with ES256. Certs are fetched and cached by an [AsyncClient],
+/// see [Self::with_client].
+#[derive(Debug, Clone)]
+pub struct AsyncIapVerifier {
+    audience: String,
+    client: AsyncClient,
+    options: ValidationOptions,
+}
+
+impl AsyncIapVerifier {
+    /// Create a new async verifier, which accepts JWTs for `audience`.
+    ///
+    /// See [crate::iap_backend_service_audience] and [crate::iap_app_engine_audience] for the format of `audience`.
+    pub fn new<S: ToString>(audience: S) -> Self {
+        let audience = audience.to_string();
+
+        Self::with_client(AsyncClient::new(&audience).certs_url(IAP_CERTS_URL), audience)
+    }
+
+    /// Create a new async verifier, which accepts JWTs for `audience`, and fetches and caches certs with `client`,
+    /// e.g. to set [AsyncClient::cache_options] or [AsyncClient::persistent_cache].
+    ///
+    /// `client` should fetch certs from [IAP_CERTS_URL], and have no client id (or `audience`).
+    /// Its default [ValidationOptions] are not used.
+    pub fn with_client<S: ToString>(client: AsyncClient, audience: S) -> Self {
+        Self {
+            client,
+            options: iap_options(ValidationOptions::default()),
+            audience: audience.to_string(),
+        }
+    }
+
+    #[inline]
+    pub fn audience(&self) -> &str {
+        &self.audience
+    }
+
+    /// The client which fetches and caches certs, e.g. for [AsyncClient::refresh_certs] or `spawn_background_refresh`.
+    #[inline]
+    pub fn client(&self) -> &AsyncClient {
+        &self.client
+    }
+
+    /// Set the default [ValidationOptions] used by [Self::validate_assertion].
+    ///
+    /// [ValidationOptions::issuers] is ignored, since the issuer is always `https://cloud.google.com/iap`.
+    /// [ValidationOptions::reject_future_iat] is always enabled.
+    pub fn validation_options(mut self, options: ValidationOptions) -> Self {
+        self.options = iap_options(options);
+
+        self
+    }
+
+    /// Do verification with the value of header `x-goog-iap-jwt-assertion`. If success, return the user data.
+    pub async fn validate_assertion<S>(&self, assertion: S) -> MyResult<IapPayload>
+        where S: AsRef<str>
+    {
+        self.validate(assertion, &self.options).await
+    }
+
+    /// Same as [Self::validate_assertion], but using `options` instead of the default [ValidationOptions] of this verifier.
+    pub async fn validate_assertion_with_options<S>(&self, assertion: S, options: &ValidationOptions) -> MyResult<IapPayload>
+        where S: AsRef<str>
+    {
+        let options = iap_options(options.clone());
+
+        self.validate(assertion, &options).await
+    }
+
+    async fn validate<S>(&self, assertion: S, options: &ValidationOptions) -> MyResult<IapPayload>
+        where S: AsRef<str>
+    {
+        let payload: IapPayload = self.client.validate_id_token_as_with_options(assertion, options).await?;
+        policy::validate_audience(&payload.aud, &self.audience)?;
+
+        Ok(payload)
+    }
+}
@@ -0,0 +1,104 @@
+#[cfg(feature = "blocking")]
+mod client;
+mod async_client;
+
+#[cfg(feature = "blocking")]
+pub use client::*;
+pub use async_client::*;
+
+use crate::{IAP_ISS, ValidationOptions};
+
+/// The url of the JWKS which signs the JWTs from Identity-Aware Proxy.
+pub const IAP_CERTS_URL: &str = "https://www.gstatic.com/iap/verify/public_key-jwk";
+
+/// The name of the header which carries the signed JWT from Identity-Aware Proxy.
+pub const IAP_JWT_ASSERTION_HEADER: &str = "x-goog-iap-jwt-assertion";
+
+/// The audience of a backend service (for Compute Engine and GKE), which is signed by Identity-Aware Proxy.
+pub fn iap_backend_service_audience<N: ToString, S: ToString>(project_number: N, backend_service_id: S) -> String {
+    format!("/projects/{}/global/backendServices/{}", project_number.to_string(), backend_service_id.to_string())
+}
+
+/// The audience of an App Engine app, which is signed by Identity-Aware Proxy.
+pub fn iap_app_engine_audience<N: ToString, S: ToString>(project_number: N, project_id: S) -> String {
+    format!("/projects/{}/apps/{}", project_number.to_string(), project_id.to_string())
+}
+
+/// JWTs from Identity-Aware Proxy are always issued by `https://cloud.google.com/iap`,
+/// and must not be issued in the future.
+pub(crate) fn iap_options(options: ValidationOptions) -> ValidationOptions {
+    options
+        .issuers([IAP_ISS])
+        .reject_future_iat(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use base64::Engine;
+    use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+    use serde_json::{json, Value};
+    use crate::{Error, IapPayload, MyResult};
+    use crate::jwt_parser::JwtParser;
+    use crate::validate::{id_token, policy};
+    use super::*;
+
+    fn audience() -> String {
+        iap_backend_service_audience(123, 456)
+    }
+
+    fn claims() -> Value {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+        json!({
+            "aud": audience(),
+            "iss": IAP_ISS,
+            "sub": "accounts.google.com:1234",
+            "email": "user@example.com",
+            "iat": now,
+            "exp": now + 600,
+        })
+    }
+
+    /// Run the claim checks of a verifier for `audience()` on an unsigned JWT with `claims`, with a client
+    /// which has no client id (as built by `new`).
+    fn validate(claims: Value, options: ValidationOptions) -> MyResult<()> {
+        let token = format!(
+            "{}.{}.",
+            BASE64_URL_SAFE_NO_PAD.encode(json!({"alg": "ES256", "kid": "k1"}).to_string()),
+            BASE64_URL_SAFE_NO_PAD.encode(claims.to_string()),
+        );
+        let parser = JwtParser::<IapPayload>::parse(&token)?;
+
+        id_token::validate_info(&[] as &[&str], &parser, &iap_options(options))?;
+        policy::validate_audience(&parser.payload.aud, &audience())
+    }
+
+    #[test]
+    fn valid() {
+        assert!(validate(claims(), ValidationOptions::new()).is_ok());
+    }
+
+    #[test]
+    fn issuer_of_iap_only() {
+        let options = ValidationOptions::new().issuers(["https://accounts.google.com"]);
+
+        // the issuers of the caller are replaced by the issuer of IAP.
+        assert!(validate(claims(), options.clone()).is_ok());
+
+        let mut claims = claims();
+        claims["iss"] = json!("https://accounts.google.com");
+        assert!(matches!(validate(claims, options), Err(Error::GoogleIssuerNotMatchError(_))));
+    }
+
+    #[test]
+    fn audience_of_other_backend_service() {
+        let mut claims = claims();
+        claims["aud"] = json!(iap_backend_service_audience(123, 789));
+
+        assert!(matches!(validate(claims.clone(), ValidationOptions::new()), Err(Error::IDTokenClientIDNotFoundError(_))));
+
+        claims["aud"] = json!(iap_app_engine_audience(123, "my-project"));
+        assert!(matches!(validate(claims, ValidationOptions::new()), Err(Error::IDTokenClientIDNotFoundError(_))));
+    }
+}
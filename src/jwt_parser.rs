@@ -59,6 +59,32 @@ impl<'a, T> JwtParser<'a, T> where T: DeserializeOwned {
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct JwtHeader {
     pub alg: String,
-    pub typ: String,
+    /// `typ` is optional in JWS, and omitted by some issuers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub typ: Option<String>,
     pub kid: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(json: &str) -> MyResult<JwtHeader> {
+        JwtParser::<serde_json::Value>::parse_header(&BASE64_URL_SAFE_NO_PAD.encode(json))
+    }
+
+    #[test]
+    fn header_typ() {
+        let jh = header(r#"{"alg":"PS256","kid":"k1","typ":"JWT"}"#).unwrap();
+        assert_eq!(jh.typ.as_deref(), Some("JWT"));
+
+        let jh = header(r#"{"alg":"PS256","kid":"k1"}"#).unwrap();
+        assert_eq!(jh.alg, "PS256");
+        assert_eq!(jh.typ, None);
+    }
+
+    #[test]
+    fn header_kid_required() {
+        assert!(header(r#"{"alg":"RS384","typ":"JWT"}"#).is_err());
+    }
+}
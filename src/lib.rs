@@ -60,6 +60,83 @@
 //! In this case, `AsyncClient` will accept all `client_id`.
 //! However, Google issuer (`iss`), expiration (`exp`) and JWT hash **CAN NOT** be skipped.
 //!
+//! **Validation options**
+//!
+//! [`ValidationOptions`] controls how the time-related claims are checked, e.g. the tolerance of clock skew:
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use google_oauth::{AsyncClient, ValidationOptions};
+//!
+//! # async fn run() {
+//! let options = ValidationOptions::new()
+//!     .leeway(Duration::from_secs(30))   // tolerance of clock skew, applied to `exp`, `nbf` and `iat`
+//!     .check_nbf(true)                   // reject tokens whose `nbf` is later than now (default)
+//!     .reject_future_iat(true)           // reject tokens whose `iat` is later than now
+//!     .max_age(Duration::from_secs(600)); // reject tokens issued more than 10 minutes ago
+//!
+//! let client = AsyncClient::new("your client id").validation_options(options.clone());
+//! // or, for a single validation:
+//! let payload = client.validate_id_token_with_options("the id_token", &options).await;
+//! # }
+//! ```
+//!
+//! If you send a `nonce` when signing in (e.g. the implicit flow or One Tap), use `validate_id_token_with_nonce` (or `ValidationOptions::nonce`) to check it.
+//!
+//! To only accept Google Workspace accounts from some domains, use `ValidationOptions::hosted_domains` (and `ValidationOptions::reject_consumer_accounts` to reject accounts without `hd`). It applies to both `id_token` and `access_token` validation.
+//!
+//! If you trust the email address of the account (e.g. for account linking), use `ValidationOptions::require_verified_email` to reject `id_token`s without a verified `email`.
+//!
+//! **Custom claims**
+//!
+//! If you need claims which are not listed in [`GooglePayload`], implement [`IDTokenClaims`] for your own type,
+//! and use `validate_id_token_as`:
+//! ```rust,no_run
+//! use google_oauth::{AsyncClient, Audience, IDTokenClaims};
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct MyClaims {
+//!     aud: Audience,
+//!     iss: String,
+//!     exp: u64,
+//!     iat: u64,
+//!     groups: Vec<String>,
+//! }
+//!
+//! impl IDTokenClaims for MyClaims {
+//!     fn aud(&self) -> &Audience { &self.aud }
+//!     fn iss(&self) -> &str { &self.iss }
+//!     fn exp(&self) -> u64 { self.exp }
+//!     fn iat(&self) -> u64 { self.iat }
+//! }
+//!
+//! # async fn run() {
+//! let client = AsyncClient::new("your client id");
+//! let claims = client.validate_id_token_as::<MyClaims, _>("the id_token").await.unwrap();
+//! println!("groups: {:?}", claims.groups);
+//! # }
+//! ```
+//!
+//! By default, only Google issuers (`https://accounts.google.com` and `accounts.google.com`) are accepted. To verify JWTs from other issuers, use `ValidationOptions::issuers`.
+//!
+//! **Offline verification**
+//!
+//! By default, certs are fetched from Google (see `certs_url`). You can provide certs from somewhere else with `cert_source`, e.g. `StaticCertSource` for in-memory certs, `FileCertSource` for a JSON file on disk, or your own implementation of `AsyncCertSource` (`CertSource` for the blocking client). Cert sources are not available with feature `wasm`: the wasm `Client` always fetches certs from its `certs_url`.
+//!
+//! Certs can be a JWKS (`{"keys": [...]}`), or a map of X.509 certificates in PEM (`{"<kid>": "-----BEGIN CERTIFICATE-----..."}`), which is published by some Google endpoints, e.g. `https://www.googleapis.com/oauth2/v1/certs`. Certificates which are not in their validity window are skipped, and the certs are rejected when none of them can be used.
+//!
+//! **Background refresh**
+//!
+//! By default, certs are fetched when a validation finds them expired. Call `spawn_background_refresh` (a tokio task for `AsyncClient`, which needs feature `background-refresh`; a thread for the blocking `Client`) to refresh certs shortly before they expire. Validations keep using the previous certs until the new ones arrive, but no longer than 5 minutes after they expire: then (or when the refresher stops) certs are fetched by the validation again.
+//!
+//! **Persistent cache**
+//!
+//! Certs are cached in memory. To keep them across restarts (e.g. serverless cold starts), use `persistent_cache(path)`: certs are loaded from the file when the client is created, and saved into it after each refresh.
+//!
+//! **Cache duration**
+//!
+//! Certs are cached according to the HTTP cache headers of the response. Use `cache_options` with `CacheOptions` to bound the cache duration (`min_ttl`, default 1 minute; `max_ttl`, default 1 day) and to add a random `jitter`, so that a fleet of instances does not refresh certs at the same instant.
+//!
 //! ### 3. Do Verification (`AccessToken`)
 //!
 //! Sometimes, Google will return an `access_token` instead of `id_token`. `Google-Oauth` still provides API for validate
@@ -89,16 +166,117 @@
 //!
 //! > For full example, please view ./example/async_client/
 //!
+//! ### 4. Do Verification (Firebase `id_token`)
+//!
+//! `id_token`s from Firebase Authentication are issued for a Firebase project. Use `AsyncFirebaseClient`
+//! (`FirebaseClient` for the blocking client) with your project id to verify them:
+//!
+//! ```rust,no_run
+//! use google_oauth::AsyncFirebaseClient;
+//!
+//! # async fn run() {
+//! let client = AsyncFirebaseClient::new("your project id");
+//!
+//! let payload = client.validate_id_token("the id_token").await.unwrap();
+//!
+//! println!("Hello, I am {}, signed in with {}", &payload.sub, &payload.firebase.sign_in_provider);
+//! # }
+//! ```
+//!
+//! Besides the checks of `id_token`, `iat` and `auth_time` must not be in the future, and `sub` (the uid) must not be empty.
+//!
+//! ### 5. Do Verification (Identity-Aware Proxy)
+//!
+//! Requests through Identity-Aware Proxy carry a JWT signed with ES256 in header `x-goog-iap-jwt-assertion`.
+//! Use `AsyncIapVerifier` (`IapVerifier` for the blocking client) with the audience of your backend to verify it:
+//!
+//! ```rust,no_run
+//! use google_oauth::{AsyncIapVerifier, iap_backend_service_audience};
+//!
+//! # async fn run() {
+//! // or `iap_app_engine_audience(project_number, project_id)` for App Engine.
+//! let verifier = AsyncIapVerifier::new(iap_backend_service_audience("project number", "backend service id"));
+//!
+//! let payload = verifier.validate_assertion("the value of x-goog-iap-jwt-assertion").await.unwrap();
+//!
+//! println!("Hello, I am {}", &payload.email);
+//! # }
+//! ```
+//!
+//! The JWT must be signed by Identity-Aware Proxy for the audience, and its `iat` must not be in the future.
+//!
+//! ### 6. Do Verification (service-to-service `id_token`)
+//!
+//! Cloud Run, Cloud Functions, Cloud Scheduler and Cloud Tasks call other services with Google-signed `id_token`s of a
+//! service account, whose audience is the url of the called service. Create a client with the url as the client id,
+//! allow the service accounts with `ValidationOptions::service_accounts`, and use `validate_service_id_token`:
+//!
+//! ```rust,no_run
+//! use google_oauth::{AsyncClient, ValidationOptions};
+//!
+//! # async fn run() {
+//! let client = AsyncClient::new("https://my-service.run.app")
+//!     .validation_options(ValidationOptions::new().service_accounts(["caller@my-project.iam.gserviceaccount.com"]));
+//!
+//! let payload = client.validate_service_id_token("the id_token").await.unwrap();
+//!
+//! // `google.compute_engine` is present if the token is requested from a Compute Engine instance with `format=full`.
+//! if let Some(compute_engine) = payload.google.and_then(|google| google.compute_engine) {
+//!     println!("called from {}", compute_engine.instance_name);
+//! }
+//! # }
+//! ```
+//!
+//! The `email` of the token must be one of the allowed service accounts, and be verified. Since any Google account
+//! can get a token for any audience, every token is rejected if no service account is allowed.
+//!
+//! ### 7. Do Verification (Pub/Sub push)
+//!
+//! Requests from a Pub/Sub push subscription (with authentication enabled) carry a Google-signed `id_token` of the push
+//! service account. Use `AsyncPubSubPushVerifier` (`PubSubPushVerifier` for the blocking client) to verify the
+//! `Authorization` header and decode the body:
+//!
+//! ```rust,no_run
+//! use google_oauth::AsyncPubSubPushVerifier;
+//!
+//! # async fn run(authorization: &str, body: &[u8]) {
+//! // the audience is the custom audience of the subscription, or the push endpoint if it is not set.
+//! let verifier = AsyncPubSubPushVerifier::new("https://my-service.run.app/push", "push@my-project.iam.gserviceaccount.com");
+//!
+//! let push = verifier.validate_push(authorization, body).await.unwrap();
+//!
+//! println!("message {} from {}", &push.envelope.message.message_id, &push.envelope.subscription);
+//! let data = push.envelope.message.decode_data().unwrap();
+//! # }
+//! ```
+//!
+//! **Cert options of the verifiers**
+//!
+//! `AsyncFirebaseClient`, `AsyncIapVerifier` and `AsyncPubSubPushVerifier` fetch and cache certs with an inner `AsyncClient` (`Client` for the blocking verifiers). To set the options of the cert cache, configure the client and pass it to `with_client`. Use `client()` to call `refresh_certs` or `spawn_background_refresh` on it:
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use google_oauth::{AsyncClient, AsyncFirebaseClient, CacheOptions, FIREBASE_CERTS_URL};
+//!
+//! # async fn run() {
+//! let client = AsyncClient::new("your project id")
+//!     .certs_url(FIREBASE_CERTS_URL)
+//!     .cache_options(CacheOptions::new().jitter(Duration::from_secs(30)));
+//!
+//! let verifier = AsyncFirebaseClient::with_client(client, "your project id");
+//! verifier.client().refresh_certs().await.unwrap();
+//! # }
+//! ```
+//!
 //! ## Algorithm Supported
 //! For validating `id_token`, Google may use these two kinds of hash algorithm to generate JWTs:
 //!
 //! - [x] RS256
-//! - [ ] ES256
+//! - [x] ES256
 //!
-//! However, I cannot find any approach to get a valid `ES256` token, and as a result, I remained a `unimplemented` branch,
-//! and return an `Err` if the JWT is `ES256` hashed.
+//! Besides, these algorithms are also supported, for JWTs which are not generated from Google:
 //!
-//! Feel free to create a new issue if you have an example. PR is welcome.
+//! - [x] RS384, RS512
+//! - [x] PS256, PS384, PS512
 //!
 //! ## Usage (blocking)
 //! `Google-Oauth` also provides a blocking client. You need to enable `blocking` feature:
@@ -139,11 +317,16 @@
 //!
 //! If you need to import `wasm` into your project, you can use `google_oauth::Client` to run async functions.
 //!
+//! Note: the wasm `Client` does not support `cert_source`, background refresh or the persistent cache. It fetches certs from `certs_url`, and caches them in memory.
+//!
+//! [`ValidationOptions`] (e.g. the leeway, `max_age` or `nonce`) and `CacheOptions` (e.g. `min_ttl`) can be set with `validation_options` and `cache_options` from Rust only. From javascript, the default options are used, so certs are cached for 1 minute at least.
+//!
 //! ## Features
 //! + `default`: enable `AsyncClient`.
 //! + `blocking`: enable `Client`.
 //! + `wasm`: disable `AsyncClient` and `Client`(`blocking`), enable `Client` (`wasm`).
 //! + `reqwest-rustls`: use rustls as the TLS backend of the Reqwest client
+//! + `background-refresh`: enable `AsyncClient::spawn_background_refresh`, which requires a [tokio](https://github.com/tokio-rs/tokio) runtime.
 //!
 
 #[cfg(feature = "blocking")]
@@ -154,9 +337,20 @@ mod output;
 
 #[cfg(feature = "wasm")]
 mod wasm;
+#[cfg(not(feature = "wasm"))]
+mod firebase;
+#[cfg(not(feature = "wasm"))]
+mod iap;
+#[cfg(not(feature = "wasm"))]
+mod pubsub;
 
+mod algorithm;
 mod jwt_parser;
 mod certs;
+#[cfg(not(feature = "wasm"))]
+mod cert_source;
+mod claims;
+mod options;
 mod validate;
 mod utils;
 mod error;
@@ -165,14 +359,25 @@ mod error;
 pub use client::*;
 #[cfg(not(feature = "wasm"))]
 pub use async_client::*;
+pub use algorithm::*;
 pub use certs::*;
+#[cfg(not(feature = "wasm"))]
+pub use cert_source::*;
+pub use claims::*;
 pub use output::*;
+pub use options::*;
 pub use error::*;
 
 type MyResult<T> = error::Result<T>;
 
 #[cfg(feature = "wasm")]
 pub use wasm::*;
+#[cfg(not(feature = "wasm"))]
+pub use firebase::*;
+#[cfg(not(feature = "wasm"))]
+pub use iap::*;
+#[cfg(not(feature = "wasm"))]
+pub use pubsub::*;
 
 #[allow(unused)]
 const GOOGLE_SA_CERTS_URL: &str = "https://www.googleapis.com/oauth2/v3/certs";
@@ -181,6 +386,18 @@ const GOOGLE_ISS: [&str; 2] = ["https://accounts.google.com", "accounts.google.c
 #[allow(unused)]
 const DEFAULT_TIMEOUT: u64 = 5u64;
 #[allow(unused)]
+const DEFAULT_FORCED_REFRESH_INTERVAL: u64 = 60u64;
+#[allow(unused)]
+const BACKGROUND_REFRESH_AHEAD: u64 = 60u64;
+#[allow(unused)]
+const BACKGROUND_REFRESH_RETRY: u64 = 10u64;
+#[allow(unused)]
+const BACKGROUND_REFRESH_GRACE: u64 = 300u64;
+#[allow(unused)]
+const FIREBASE_ISS_PREFIX: &str = "https://securetoken.google.com/";
+#[allow(unused)]
+const IAP_ISS: &str = "https://cloud.google.com/iap";
+#[allow(unused)]
 const GOOGLE_OAUTH_V3_USER_INFO_API: &str = "https://www.googleapis.com/oauth2/v3/userinfo";
 
 #[cfg(all(feature = "wasm", feature = "blocking"))]
@@ -0,0 +1,280 @@
+use std::time::Duration;
+#[cfg(not(feature = "wasm"))]
+use std::time::Instant;
+#[cfg(feature = "wasm")]
+use web_time::Instant;
+use crate::{Certs, GOOGLE_ISS, utils};
+
+/// `ValidationOptions` controls how the claims of an `id_token` are checked.
+///
+/// It can be attached to a client as the default options, or be passed for a single validation.
+#[derive(Debug, Clone)]
+pub struct ValidationOptions {
+    pub(crate) leeway: Duration,
+    pub(crate) check_nbf: bool,
+    pub(crate) reject_future_iat: bool,
+    pub(crate) max_age: Option<Duration>,
+    pub(crate) nonce: Option<String>,
+    pub(crate) hosted_domains: Vec<String>,
+    pub(crate) reject_consumer_accounts: bool,
+    pub(crate) require_verified_email: bool,
+    pub(crate) service_accounts: Vec<String>,
+    pub(crate) issuers: Vec<String>,
+}
+
+impl ValidationOptions {
+    /// Create the default options: Google issuers are accepted, no leeway, `nbf` is checked, `iat` is not checked.
+    pub fn new() -> Self {
+        Self {
+            leeway: Duration::ZERO,
+            check_nbf: true,
+            reject_future_iat: false,
+            max_age: None,
+            nonce: None,
+            hosted_domains: Vec::new(),
+            reject_consumer_accounts: false,
+            require_verified_email: false,
+            service_accounts: Vec::new(),
+            issuers: GOOGLE_ISS.iter().map(|iss| iss.to_string()).collect(),
+        }
+    }
+
+    /// Set the tolerance of clock skew, which is applied to `exp`, `nbf` and `iat`.
+    /// Only whole seconds are used.
+    pub fn leeway(mut self, d: Duration) -> Self {
+        self.leeway = d;
+        self
+    }
+
+    /// Reject tokens whose `nbf` (if provided) is later than now. Default is `true`.
+    pub fn check_nbf(mut self, check: bool) -> Self {
+        self.check_nbf = check;
+        self
+    }
+
+    /// Reject tokens whose `iat` is later than now. Default is `false`.
+    pub fn reject_future_iat(mut self, reject: bool) -> Self {
+        self.reject_future_iat = reject;
+        self
+    }
+
+    /// Reject tokens which were issued (`iat`) more than `d` ago. Default is no limit.
+    pub fn max_age(mut self, d: Duration) -> Self {
+        self.max_age = Some(d);
+        self
+    }
+
+    /// Require the `nonce` claim to be equal to `nonce`. Default is not checked.
+    pub fn nonce<S: ToString>(mut self, nonce: S) -> Self {
+        self.nonce = Some(nonce.to_string());
+        self
+    }
+
+    /// Only accept Google Workspace accounts from these hosted domains (the `hd` claim).
+    /// Default is empty, which means all domains (and consumer accounts) are accepted.
+    /// Not checked by `validate_service_id_token`, since service accounts have no `hd` claim.
+    pub fn hosted_domains<T, V>(mut self, domains: T) -> Self
+        where
+            T: AsRef<[V]>,
+            V: AsRef<str>,
+    {
+        self.hosted_domains = domains
+            .as_ref()
+            .iter()
+            .map(|d| d.as_ref())
+            .filter(|d| !d.is_empty())
+            .map(|d| d.to_string())
+            .collect();
+        self
+    }
+
+    /// Reject consumer accounts, which have no `hd` claim. Default is `false`.
+    /// Not checked by `validate_service_id_token`.
+    pub fn reject_consumer_accounts(mut self, reject: bool) -> Self {
+        self.reject_consumer_accounts = reject;
+        self
+    }
+
+    /// Require the `email` claim to be present, and `email_verified` to be `true`. Default is `false`.
+    ///
+    /// Enable it if you trust the email address of the account, e.g. for account linking.
+    pub fn require_verified_email(mut self, require: bool) -> Self {
+        self.require_verified_email = require;
+        self
+    }
+
+    /// The service accounts accepted by `validate_service_id_token`, e.g. for Google-signed tokens from Cloud Run
+    /// or Cloud Tasks: the `email` claim must be one of them, and `email_verified` must be `true`.
+    /// Other validations, e.g. `validate_id_token`, do not check it. Default is empty, which rejects every service token.
+    pub fn service_accounts<T, V>(mut self, emails: T) -> Self
+        where
+            T: AsRef<[V]>,
+            V: AsRef<str>,
+    {
+        self.service_accounts = emails
+            .as_ref()
+            .iter()
+            .map(|e| e.as_ref())
+            .filter(|e| !e.is_empty())
+            .map(|e| e.to_string())
+            .collect();
+        self
+    }
+
+    /// Set the accepted issuers (the `iss` claim).
+    /// Default is `https://accounts.google.com` and `accounts.google.com`. Empty issuers will be ignored.
+    pub fn issuers<T, V>(mut self, issuers: T) -> Self
+        where
+            T: AsRef<[V]>,
+            V: AsRef<str>,
+    {
+        let issuers: Vec<String> = issuers
+            .as_ref()
+            .iter()
+            .map(|iss| iss.as_ref())
+            .filter(|iss| !iss.is_empty())
+            .map(|iss| iss.to_string())
+            .collect();
+
+        if !issuers.is_empty() {
+            self.issuers = issuers;
+        }
+
+        self
+    }
+
+    /// The options without [Self::hosted_domains] and [Self::reject_consumer_accounts],
+    /// since service accounts have no `hd` claim.
+    #[cfg(not(feature = "wasm"))]
+    pub(crate) fn without_hosted_domains(&self) -> Self {
+        Self {
+            hosted_domains: Vec::new(),
+            reject_consumer_accounts: false,
+            ..self.clone()
+        }
+    }
+}
+
+impl Default for ValidationOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `CacheOptions` controls how long fetched certs are cached.
+///
+/// The cache duration given by the cert source (e.g. `max-age` of the response) is reduced by a random jitter,
+/// so that a fleet of instances does not refresh certs at the same instant, then clamped into `[min_ttl, max_ttl]`.
+#[derive(Debug, Clone, Copy)]
+pub struct CacheOptions {
+    pub(crate) min_ttl: Duration,
+    pub(crate) max_ttl: Duration,
+    pub(crate) jitter: Duration,
+}
+
+impl CacheOptions {
+    /// Create the default options: certs are cached for 1 minute at least, and 1 day at most, without jitter.
+    pub fn new() -> Self {
+        Self {
+            min_ttl: Duration::from_secs(60),
+            max_ttl: Duration::from_secs(24 * 60 * 60),
+            jitter: Duration::ZERO,
+        }
+    }
+
+    /// Set the minimum cache duration. Default is 1 minute.
+    pub fn min_ttl(mut self, d: Duration) -> Self {
+        self.min_ttl = d;
+        self
+    }
+
+    /// Set the maximum cache duration. Default is 1 day.
+    /// If it is less than the minimum cache duration, certs are always cached for the minimum cache duration.
+    pub fn max_ttl(mut self, d: Duration) -> Self {
+        self.max_ttl = d;
+        self
+    }
+
+    /// Set the maximum random jitter, which is subtracted from the cache duration. Default is zero.
+    pub fn jitter(mut self, d: Duration) -> Self {
+        self.jitter = d;
+        self
+    }
+
+    /// Apply the jitter to the `cache_until` of `certs`, then clamp it, so it never falls below `min_ttl`.
+    pub(crate) fn apply(&self, certs: &mut Certs) {
+        let now = Instant::now();
+
+        let ttl = certs
+            .cache_until()
+            .map(|until| until.saturating_duration_since(now))
+            .unwrap_or_default()
+            .saturating_sub(utils::random_duration(self.jitter))
+            .clamp(self.min_ttl, self.max_ttl.max(self.min_ttl));
+
+        certs.set_cache_until(now + ttl);
+    }
+}
+
+impl Default for CacheOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Apply `options` to certs cached for `ttl`, and return the cache duration (in seconds) afterwards.
+    fn apply(options: CacheOptions, ttl: u64) -> u64 {
+        let mut certs = Certs::default();
+        certs.set_cache_until(Instant::now() + Duration::from_secs(ttl));
+
+        options.apply(&mut certs);
+
+        // round up, since some time has passed since `apply`.
+        certs.cache_until().unwrap().saturating_duration_since(Instant::now()).as_secs_f64().ceil() as u64
+    }
+
+    #[test]
+    fn clamp_to_min_ttl() {
+        assert_eq!(apply(CacheOptions::new(), 0), 60);
+        assert_eq!(apply(CacheOptions::new(), 30), 60);
+        assert_eq!(apply(CacheOptions::new().min_ttl(Duration::from_secs(10)), 30), 30);
+    }
+
+    #[test]
+    fn clamp_to_max_ttl() {
+        assert_eq!(apply(CacheOptions::new(), 3600), 3600);
+        assert_eq!(apply(CacheOptions::new().max_ttl(Duration::from_secs(600)), 3600), 600);
+    }
+
+    #[test]
+    fn max_ttl_less_than_min_ttl() {
+        let options = CacheOptions::new()
+            .min_ttl(Duration::from_secs(300))
+            .max_ttl(Duration::from_secs(100));
+
+        // `min_ttl` wins.
+        assert_eq!(apply(options, 0), 300);
+        assert_eq!(apply(options, 3600), 300);
+    }
+
+    #[test]
+    fn jitter_never_below_min_ttl() {
+        let options = CacheOptions::new()
+            .min_ttl(Duration::from_secs(60))
+            .jitter(Duration::from_secs(50));
+
+        for _ in 0..100 {
+            assert!((60..=70).contains(&apply(options, 70)));
+            assert_eq!(apply(options, 0), 60);
+        }
+
+        // the jitter is only subtracted.
+        for _ in 0..100 {
+            assert!((3550..=3600).contains(&apply(options, 3600)));
+        }
+    }
+}
@@ -1,4 +1,9 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use serde::{Serialize, Deserialize};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use crate::MyResult;
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
@@ -10,7 +15,8 @@ use wasm_bindgen::prelude::*;
 #[cfg_attr(feature = "wasm", wasm_bindgen(getter_with_clone))]
 pub struct GooglePayload {
     // These fields are marked `always`.
-    pub aud: String,
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub aud: Audience,
     pub exp: u64,
     pub iat: u64,
     pub iss: String,
@@ -32,6 +38,114 @@ pub struct GooglePayload {
     // These fields not list in document, but may exist
     pub nbf: Option<u64>,
     pub jti: Option<String>,
+
+    /// All other claims which are not listed above, e.g. `google`, `email_domain` or `firebase`.
+    #[serde(flatten)]
+    #[cfg_attr(feature = "wasm", wasm_bindgen(skip))]
+    pub extra: Map<String, Value>,
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl GooglePayload {
+    /// All audiences of the token, since [Audience] cannot be exported to javascript.
+    #[wasm_bindgen(getter = aud)]
+    pub fn audiences(&self) -> Vec<String> {
+        self.aud.iter().map(|aud| aud.to_string()).collect()
+    }
+}
+
+impl GooglePayload {
+    /// Get a claim which is not listed in [GooglePayload], see [GooglePayload::extra].
+    #[inline]
+    pub fn extra_claim(&self, name: &str) -> Option<&Value> {
+        self.extra.get(name)
+    }
+
+    /// Get a claim which is not listed in [GooglePayload], and deserialize it as `T`.
+    ///
+    /// Return `Ok(None)` if the claim does not exist, or `Err` if it cannot be deserialized as `T`.
+    pub fn extra_claim_as<T: DeserializeOwned>(&self, name: &str) -> MyResult<Option<T>> {
+        match self.extra.get(name) {
+            Some(value) => Ok(Some(T::deserialize(value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get a string claim which is not listed in [GooglePayload].
+    #[inline]
+    pub fn extra_claim_str(&self, name: &str) -> Option<&str> {
+        self.extra.get(name).and_then(|v| v.as_str())
+    }
+
+    /// Get a boolean claim which is not listed in [GooglePayload].
+    #[inline]
+    pub fn extra_claim_bool(&self, name: &str) -> Option<bool> {
+        self.extra.get(name).and_then(|v| v.as_bool())
+    }
+
+    /// Get an integer claim which is not listed in [GooglePayload].
+    #[inline]
+    pub fn extra_claim_u64(&self, name: &str) -> Option<u64> {
+        self.extra.get(name).and_then(|v| v.as_u64())
+    }
+}
+
+/// `Audience` is the `aud` claim of a JWT, which can be a single string or an array of strings.
+///
+/// see https://www.rfc-editor.org/rfc/rfc7519#section-4.1.3 for more info.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Audience {
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl Audience {
+    /// Iterate over all audiences.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        let audiences = match self {
+            Self::Single(aud) => std::slice::from_ref(aud),
+            Self::Multiple(auds) => auds.as_slice(),
+        };
+
+        audiences.iter().map(|aud| aud.as_str())
+    }
+
+    /// Check if `aud` is one of the audiences.
+    #[inline]
+    pub fn contains(&self, aud: &str) -> bool {
+        self.iter().any(|a| a == aud)
+    }
+
+    /// Check if there are more than one audiences.
+    #[inline]
+    pub fn is_multiple(&self) -> bool {
+        self.iter().count() > 1
+    }
+}
+
+impl Display for Audience {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Single(aud) => f.write_str(aud),
+            Self::Multiple(auds) => write!(f, "{:?}", auds),
+        }
+    }
+}
+
+impl From<String> for Audience {
+    #[inline]
+    fn from(aud: String) -> Self {
+        Self::Single(aud)
+    }
+}
+
+impl From<&str> for Audience {
+    #[inline]
+    fn from(aud: &str) -> Self {
+        Self::Single(aud.to_string())
+    }
 }
 
 /// `GoogleAccessTokenPayload` is the user data when using access token
@@ -50,4 +164,238 @@ pub struct GoogleAccessTokenPayload {
     pub given_name: Option<String>,
     pub email: Option<String>,
     pub email_verified: Option<bool>,
+    pub hd: Option<String>,
+}
+
+/// `FirebasePayload` is the user data in an `id_token` from Firebase Authentication.
+///
+/// see https://firebase.google.com/docs/auth/admin/verify-id-tokens for more info.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct FirebasePayload {
+    // These fields are marked `always`.
+    pub aud: Audience,
+    pub exp: u64,
+    pub iat: u64,
+    pub iss: String,
+    /// The uid of the user.
+    pub sub: String,
+    /// The time when the user authenticated.
+    pub auth_time: u64,
+    pub firebase: FirebaseClaims,
+
+    // These fields are optional.
+    pub user_id: Option<String>,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub phone_number: Option<String>,
+    pub name: Option<String>,
+    pub picture: Option<String>,
+
+    /// All other claims which are not listed above, e.g. custom claims set by the Admin SDK.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// `FirebaseClaims` is the `firebase` claim of [FirebasePayload].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct FirebaseClaims {
+    /// The provider used to sign in, e.g. `google.com`, `password`, `phone` or `custom`.
+    pub sign_in_provider: String,
+    /// The identifiers of the user, by provider, e.g. `{"google.com": ["1234567890"], "email": ["user@example.com"]}`.
+    #[serde(default)]
+    pub identities: HashMap<String, Vec<String>>,
+    /// The tenant id, when using multi-tenancy.
+    pub tenant: Option<String>,
+    /// The second factor used to sign in, when using multi-factor authentication.
+    pub sign_in_second_factor: Option<String>,
+    pub second_factor_identifier: Option<String>,
+}
+
+/// `IapPayload` is the user data in the signed header (`x-goog-iap-jwt-assertion`) from Identity-Aware Proxy.
+///
+/// see https://cloud.google.com/iap/docs/signed-headers-howto for more info.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct IapPayload {
+    // These fields are marked `always`.
+    pub aud: Audience,
+    pub exp: u64,
+    pub iat: u64,
+    pub iss: String,
+    pub sub: String,
+    pub email: String,
+
+    // These fields are optional.
+    pub hd: Option<String>,
+    pub google: Option<IapGoogleClaims>,
+
+    /// All other claims which are not listed above.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// `IapGoogleClaims` is the `google` claim of [IapPayload].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct IapGoogleClaims {
+    /// The access levels (of Access Context Manager) which the request satisfies,
+    /// e.g. `accessPolicies/<policy>/accessLevels/<level>`.
+    #[serde(default)]
+    pub access_levels: Vec<String>,
+}
+
+/// `ServiceIdTokenPayload` is the data in a Google-signed `id_token` of a service account, e.g. when
+/// Cloud Run, Cloud Functions, Cloud Scheduler or Cloud Tasks call another service.
+///
+/// see https://cloud.google.com/compute/docs/instances/verifying-instance-identity for more info about `google`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ServiceIdTokenPayload {
+    // These fields are marked `always`.
+    pub aud: Audience,
+    pub exp: u64,
+    pub iat: u64,
+    pub iss: String,
+    /// The unique id of the service account.
+    pub sub: String,
+
+    // These fields are optional.
+    pub azp: Option<String>,
+    /// The email of the service account.
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub google: Option<ServiceGoogleClaims>,
+
+    /// All other claims which are not listed above.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// `ServiceGoogleClaims` is the `google` claim of [ServiceIdTokenPayload].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ServiceGoogleClaims {
+    /// Present when the token is requested with `format=full` from the metadata server of a Compute Engine instance.
+    pub compute_engine: Option<ComputeEngineClaims>,
+}
+
+/// `ComputeEngineClaims` describes the Compute Engine instance which requested the token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct ComputeEngineClaims {
+    pub project_id: String,
+    pub project_number: u64,
+    pub zone: String,
+    pub instance_id: String,
+    pub instance_name: String,
+    pub instance_creation_timestamp: Option<u64>,
+    /// Present when the token is requested with `licenses=TRUE`.
+    pub license_id: Option<Vec<String>>,
+}
+
+/// `PubSubPushEnvelope` is the body of a request from a Pub/Sub push subscription.
+///
+/// see https://cloud.google.com/pubsub/docs/push#receive_push for more info.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct PubSubPushEnvelope {
+    pub message: PubSubMessage,
+    /// The full name of the subscription, e.g. `projects/<project>/subscriptions/<subscription>`.
+    pub subscription: String,
+    /// Present when a dead-letter policy is set on the subscription.
+    #[serde(rename = "deliveryAttempt")]
+    pub delivery_attempt: Option<u32>,
+}
+
+/// `PubSubMessage` is the message in [PubSubPushEnvelope].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct PubSubMessage {
+    /// The data of the message, encoded in base64. See [PubSubMessage::decode_data].
+    #[serde(default)]
+    pub data: String,
+    #[serde(default)]
+    pub attributes: HashMap<String, String>,
+    #[serde(rename = "messageId")]
+    pub message_id: String,
+    /// The time when the message was published, in RFC 3339.
+    #[serde(rename = "publishTime")]
+    pub publish_time: String,
+    #[serde(rename = "orderingKey")]
+    pub ordering_key: Option<String>,
+}
+
+impl PubSubMessage {
+    /// Decode the base64 `data` of the message.
+    pub fn decode_data(&self) -> MyResult<Vec<u8>> {
+        use base64::Engine;
+
+        Ok(base64::prelude::BASE64_STANDARD.decode(&self.data)?)
+    }
+}
+
+/// `PubSubPush` is a verified request from a Pub/Sub push subscription.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct PubSubPush {
+    /// The claims of the bearer token, which is signed by Google for the push service account.
+    pub claims: ServiceIdTokenPayload,
+    pub envelope: PubSubPushEnvelope,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn audience_single() {
+        let aud: Audience = serde_json::from_str(r#""client-a""#).unwrap();
+
+        assert_eq!(aud, Audience::Single("client-a".to_string()));
+        assert!(aud.contains("client-a"));
+        assert!(!aud.contains("client-b"));
+        assert!(!aud.is_multiple());
+    }
+
+    #[test]
+    fn audience_multiple() {
+        let aud: Audience = serde_json::from_str(r#"["client-a", "client-b"]"#).unwrap();
+
+        assert!(aud.contains("client-a"));
+        assert!(aud.contains("client-b"));
+        assert!(aud.is_multiple());
+        assert_eq!(aud.iter().collect::<Vec<_>>(), ["client-a", "client-b"]);
+
+        // a single audience in an array is not multiple.
+        let aud: Audience = serde_json::from_str(r#"["client-a"]"#).unwrap();
+        assert!(!aud.is_multiple());
+    }
+
+    #[test]
+    fn audience_invalid() {
+        assert!(serde_json::from_str::<Audience>("1").is_err());
+        assert!(serde_json::from_str::<Audience>("[1]").is_err());
+    }
+
+    #[test]
+    fn iap_access_levels() {
+        let payload: IapPayload = serde_json::from_str(r#"{
+            "aud": "/projects/123/global/backendServices/456",
+            "exp": 1700003600,
+            "iat": 1700000000,
+            "iss": "https://cloud.google.com/iap",
+            "sub": "accounts.google.com:1234",
+            "email": "user@example.com",
+            "google": {"access_levels": ["accessPolicies/1/accessLevels/corp"]}
+        }"#).unwrap();
+
+        let google = payload.google.unwrap();
+        assert_eq!(google.access_levels, ["accessPolicies/1/accessLevels/corp"]);
+
+        // `access_levels` is omitted if the request satisfies no access level.
+        let google: IapGoogleClaims = serde_json::from_str("{}").unwrap();
+        assert!(google.access_levels.is_empty());
+    }
 }
@@ -0,0 +1,78 @@
+use crate::{AsyncClient, MyResult, PubSubPush, ServiceIdTokenPayload, ValidationOptions};
+use crate::pubsub::{bearer_token, pubsub_options};
+
+/// AsyncPubSubPushVerifier is an async client to verify requests from a Pub/Sub push subscription.
+///
+/// The request carries a Google-signed `id_token` of the push service account in its `Authorization` header,
+/// which is verified in the same way as [AsyncClient::validate_service_id_token].
+#[derive(Debug, Clone)]
+pub struct AsyncPubSubPushVerifier {
+    service_account: String,
+    client: AsyncClient,
+    options: ValidationOptions,
+}
+
+impl AsyncPubSubPushVerifier {
+    /// Create a new async verifier, which accepts tokens for `audience` of `service_account`.
+    ///
+    /// `audience` is the custom audience of the push subscription, or the push endpoint if it is not set.
+    pub fn new<A: ToString, S: ToString>(audience: A, service_account: S) -> Self {
+        Self::with_client(AsyncClient::new(audience), service_account)
+    }
+
+    /// Create a new async verifier, which accepts tokens of `service_account`, and fetches and caches certs with `client`,
+    /// e.g. to set [AsyncClient::cache_options] or [AsyncClient::persistent_cache].
+    ///
+    /// The client id of `client` is the audience of the tokens. Its default [ValidationOptions] are not used.
+    pub fn with_client<S: ToString>(client: AsyncClient, service_account: S) -> Self {
+        let service_account = service_account.to_string();
+
+        Self {
+            client,
+            options: pubsub_options(&service_account, ValidationOptions::default()),
+            service_account,
+        }
+    }
+
+    #[inline]
+    pub fn service_account(&self) -> &str {
+        &self.service_account
+    }
+
+    /// The client which fetches and caches certs, e.g. for [AsyncClient::refresh_certs] or `spawn_background_refresh`.
+    #[inline]
+    pub fn client(&self) -> &AsyncClient {
+        &self.client
+    }
+
+    /// Set the [ValidationOptions] used by this verifier.
+    ///
+    /// [ValidationOptions::service_accounts] is ignored, since only the push service account is allowed.
+    pub fn validation_options(mut self, options: ValidationOptions) -> Self {
+        self.options = pubsub_options(&self.service_account, options);
+
+        self
+    }
+
+    /// Do verification with the `Authorization` header (`Bearer <token>`) of a push request.
+    /// If success, return the claims of the token.
+    pub async fn validate_authorization<S>(&self, authorization: S) -> MyResult<ServiceIdTokenPayload>
+        where S: AsRef<str>
+    {
+        let token = bearer_token(authorization.as_ref())?;
+
+        self.client.validate_service_id_token_with_options(token, &self.options).await
+    }
+
+    /// Do verification with the `Authorization` header of a push request, then decode its `body`.
+    pub async fn validate_push<S, B>(&self, authorization: S, body: B) -> MyResult<PubSubPush>
+        where
+            S: AsRef<str>,
+            B: AsRef<[u8]>,
+    {
+        let claims = self.validate_authorization(authorization).await?;
+        let envelope = serde_json::from_slice(body.as_ref())?;
+
+        Ok(PubSubPush { claims, envelope })
+    }
+}
@@ -0,0 +1,60 @@
+#[cfg(feature = "blocking")]
+mod client;
+mod async_client;
+
+#[cfg(feature = "blocking")]
+pub use client::*;
+pub use async_client::*;
+
+use crate::{AuthorizationHeaderInvalidError, MyResult, ValidationOptions};
+
+/// The push service account is the only allowed service account.
+fn pubsub_options(service_account: &str, options: ValidationOptions) -> ValidationOptions {
+    options.service_accounts([service_account])
+}
+
+/// Get the token from an `Authorization` header like `Bearer <token>`.
+fn bearer_token(authorization: &str) -> MyResult<&str> {
+    let (scheme, token) = authorization
+        .trim()
+        .split_once(' ')
+        .ok_or_else(|| AuthorizationHeaderInvalidError::new("missing token"))?;
+
+    if !scheme.eq_ignore_ascii_case("Bearer") {
+        Err(AuthorizationHeaderInvalidError::new(format!("expected Bearer scheme, but get {}", scheme)))?
+    }
+
+    let token = token.trim();
+    if token.is_empty() {
+        Err(AuthorizationHeaderInvalidError::new("missing token"))?
+    }
+
+    Ok(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Error;
+    use super::*;
+
+    #[test]
+    fn bearer() {
+        assert_eq!(bearer_token("Bearer eyJ.eyJ.sig").unwrap(), "eyJ.eyJ.sig");
+        assert_eq!(bearer_token("bearer eyJ.eyJ.sig").unwrap(), "eyJ.eyJ.sig");
+        assert_eq!(bearer_token("  Bearer   eyJ.eyJ.sig  ").unwrap(), "eyJ.eyJ.sig");
+    }
+
+    #[test]
+    fn bearer_wrong_scheme() {
+        assert!(matches!(bearer_token("Basic dXNlcjpwYXNz"), Err(Error::AuthorizationHeaderInvalidError(_))));
+        assert!(matches!(bearer_token("Bearer"), Err(Error::AuthorizationHeaderInvalidError(_))));
+        assert!(matches!(bearer_token("eyJ.eyJ.sig"), Err(Error::AuthorizationHeaderInvalidError(_))));
+    }
+
+    #[test]
+    fn bearer_empty_token() {
+        assert!(matches!(bearer_token(""), Err(Error::AuthorizationHeaderInvalidError(_))));
+        assert!(matches!(bearer_token("Bearer "), Err(Error::AuthorizationHeaderInvalidError(_))));
+        assert!(matches!(bearer_token("Bearer  \t "), Err(Error::AuthorizationHeaderInvalidError(_))));
+    }
+}
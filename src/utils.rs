@@ -1,26 +1,155 @@
-use reqwest::header::HeaderValue;
+#[cfg(feature = "wasm")]
+use web_time::{SystemTime, UNIX_EPOCH};
+#[cfg(not(feature = "wasm"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+use reqwest::header::{AGE, CACHE_CONTROL, DATE, EXPIRES, HeaderMap};
 
-#[inline]
-pub fn parse_max_age_from_async_resp(resp: &reqwest::Response) -> u64 {
-    parse_max_age_from_header(resp.headers().get("cache-control"))
+/// Parse how long (in seconds) a response can be cached, from its `Cache-Control`, `Age`, `Expires` and `Date` headers.
+///
+/// `no-cache` and `no-store` mean the response should not be reused, so `0` is returned.
+pub fn parse_ttl_from_headers(headers: &HeaderMap) -> u64 {
+    let directives: Vec<String> = headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|val| val.to_str().ok())
+        .flat_map(|val| val.split(','))
+        .map(|part| part.trim().to_lowercase())
+        .collect();
+
+    if directives.iter().any(|d| d == "no-cache" || d == "no-store") {
+        return 0;
+    }
+
+    let max_age: Option<u64> = directives
+        .iter()
+        .find(|d| d.starts_with("max-age"))
+        .map(|max_age| max_age.splitn(2, '=').last().unwrap_or("0").trim().parse().unwrap_or_default());
+
+    if let Some(max_age) = max_age {
+        // the response may have been cached by proxies for `age` seconds already.
+        let age = header_str(headers, AGE)
+            .and_then(|age| age.trim().parse::<u64>().ok())
+            .unwrap_or_default();
+
+        return max_age.saturating_sub(age);
+    }
+
+    // `Expires` is only used when `max-age` is missing.
+    match header_str(headers, EXPIRES).and_then(parse_unix_time) {
+        Some(expires) => {
+            let now = header_str(headers, DATE)
+                .and_then(parse_unix_time)
+                .or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs()))
+                .unwrap_or_default();
+
+            expires.saturating_sub(now)
+        }
+        None => 0,
+    }
 }
 
-#[cfg(feature = "blocking")]
-#[inline]
-pub fn parse_max_age_from_resp(resp: &reqwest::blocking::Response) -> u64 {
-    parse_max_age_from_header(resp.headers().get("cache-control"))
+/// Get the `ETag` and `Last-Modified` headers, which are used in conditional requests.
+#[cfg(not(feature = "wasm"))]
+pub fn parse_validators_from_headers(headers: &HeaderMap) -> (Option<String>, Option<String>) {
+    (
+        header_str(headers, reqwest::header::ETAG).map(|etag| etag.to_string()),
+        header_str(headers, reqwest::header::LAST_MODIFIED).map(|last_modified| last_modified.to_string()),
+    )
 }
 
-fn parse_max_age_from_header(val: Option<&HeaderValue>) -> u64 {
-    match val {
-        Some(val) => val
-            .to_str()
-            .unwrap_or("")
-            .split(',')
-            .map(|part| part.trim().to_lowercase())
-            .find(|part| part.starts_with("max-age"))
-            .map(|max_age| max_age.splitn(2, '=').last().unwrap_or("0").parse().unwrap_or_default())
-            .unwrap_or_default(),
-        None => 0,
+fn header_str(headers: &HeaderMap, name: reqwest::header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|val| val.to_str().ok())
+}
+
+fn parse_unix_time(date: &str) -> Option<u64> {
+    httpdate::parse_http_date(date)
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+}
+
+/// A random duration in `[0, max]`, in milliseconds. Zero is returned if no randomness is available.
+pub fn random_duration(max: std::time::Duration) -> std::time::Duration {
+    let max = max.as_millis() as u64;
+    if max == 0 {
+        return std::time::Duration::ZERO;
+    }
+
+    let mut buf = [0u8; 8];
+    if getrandom::getrandom(&mut buf).is_err() {
+        return std::time::Duration::ZERO;
+    }
+
+    std::time::Duration::from_millis(u64::from_le_bytes(buf) % (max + 1))
+}
+
+/// Counts the running background refreshers of a client. It is created when a refresher is spawned, and dropped
+/// whenever the refresher stops, e.g. its task is aborted, its runtime shuts down, or its thread panics.
+#[cfg(any(feature = "blocking", feature = "background-refresh"))]
+pub(crate) struct RefresherGuard(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+#[cfg(any(feature = "blocking", feature = "background-refresh"))]
+impl RefresherGuard {
+    pub fn new(refreshers: std::sync::Arc<std::sync::atomic::AtomicUsize>) -> Self {
+        refreshers.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Self(refreshers)
+    }
+}
+
+#[cfg(any(feature = "blocking", feature = "background-refresh"))]
+impl Drop for RefresherGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+    use super::*;
+
+    fn headers(pairs: &[(reqwest::header::HeaderName, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+
+        for (name, val) in pairs {
+            headers.append(name, HeaderValue::from_str(val).unwrap());
+        }
+
+        headers
+    }
+
+    #[test]
+    fn ttl_max_age() {
+        assert_eq!(parse_ttl_from_headers(&headers(&[(CACHE_CONTROL, "public, max-age=19100, must-revalidate, no-transform")])), 19100);
+        assert_eq!(parse_ttl_from_headers(&headers(&[(CACHE_CONTROL, "Max-Age=60")])), 60);
+        assert_eq!(parse_ttl_from_headers(&headers(&[])), 0);
+    }
+
+    #[test]
+    fn ttl_age() {
+        assert_eq!(parse_ttl_from_headers(&headers(&[(CACHE_CONTROL, "max-age=19100"), (AGE, "100")])), 19000);
+        assert_eq!(parse_ttl_from_headers(&headers(&[(CACHE_CONTROL, "max-age=100"), (AGE, "200")])), 0);
+        assert_eq!(parse_ttl_from_headers(&headers(&[(CACHE_CONTROL, "max-age=100"), (AGE, "invalid")])), 100);
+    }
+
+    #[test]
+    fn ttl_no_cache() {
+        assert_eq!(parse_ttl_from_headers(&headers(&[(CACHE_CONTROL, "no-cache, max-age=100")])), 0);
+        assert_eq!(parse_ttl_from_headers(&headers(&[(CACHE_CONTROL, "max-age=100"), (CACHE_CONTROL, "No-Store")])), 0);
+    }
+
+    #[test]
+    fn ttl_expires() {
+        let expires = "Wed, 21 Oct 2015 08:28:00 GMT";
+
+        assert_eq!(parse_ttl_from_headers(&headers(&[(EXPIRES, expires), (DATE, "Wed, 21 Oct 2015 07:28:00 GMT")])), 3600);
+        // expired already.
+        assert_eq!(parse_ttl_from_headers(&headers(&[(EXPIRES, expires), (DATE, "Wed, 21 Oct 2015 09:28:00 GMT")])), 0);
+        // compared with the current time without `Date`.
+        assert_eq!(parse_ttl_from_headers(&headers(&[(EXPIRES, expires)])), 0);
+        // `max-age` takes precedence.
+        assert_eq!(parse_ttl_from_headers(&headers(&[(EXPIRES, expires), (CACHE_CONTROL, "max-age=60")])), 60);
+
+        assert_eq!(parse_ttl_from_headers(&headers(&[(EXPIRES, "0"), (DATE, "Wed, 21 Oct 2015 07:28:00 GMT")])), 0);
     }
 }
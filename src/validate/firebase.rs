@@ -0,0 +1,143 @@
+#[cfg(feature = "wasm")]
+use web_time::{SystemTime, UNIX_EPOCH};
+#[cfg(not(feature = "wasm"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{FirebasePayload, IDTokenAuthTimeInFutureError, IDTokenSubjectInvalidError, MyResult, ValidationOptions};
+use crate::validate::policy;
+
+/// The max length of a Firebase uid.
+const MAX_SUBJECT_LENGTH: usize = 128;
+
+/// Check the claims which are specific to a Firebase `id_token`: `aud`, `sub` and `auth_time`.
+pub fn validate_firebase_info(project_id: &str, payload: &FirebasePayload, options: &ValidationOptions) -> MyResult<()> {
+    policy::validate_audience(&payload.aud, project_id)?;
+
+    if payload.sub.is_empty() || payload.sub.chars().count() > MAX_SUBJECT_LENGTH {
+        Err(IDTokenSubjectInvalidError::new(&payload.sub))?
+    }
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+    if payload.auth_time > now.saturating_add(options.leeway.as_secs()) {
+        Err(IDTokenAuthTimeInFutureError::new(now, payload.auth_time))?
+    }
+
+    Ok(())
+}
+
+#[cfg(all(test, not(feature = "wasm")))]
+mod tests {
+    use std::time::Duration;
+    use base64::Engine;
+    use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+    use serde_json::{json, Value};
+    use crate::Error;
+    use crate::firebase::firebase_options;
+    use crate::jwt_parser::JwtParser;
+    use crate::validate::id_token;
+    use super::*;
+
+    const PROJECT_ID: &str = "my-project";
+
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
+
+    fn claims() -> Value {
+        let now = now();
+
+        json!({
+            "aud": PROJECT_ID,
+            "iss": format!("https://securetoken.google.com/{}", PROJECT_ID),
+            "sub": "uid-1",
+            "iat": now,
+            "exp": now + 3600,
+            "auth_time": now - 60,
+            "firebase": {"sign_in_provider": "google.com"},
+        })
+    }
+
+    /// Run the claim checks of a Firebase client for `PROJECT_ID` on an unsigned token with `claims`:
+    /// signatures are not checked here. A client built with `with_client` may have no client id.
+    fn validate(claims: Value, client_ids: &[&str], options: ValidationOptions) -> MyResult<()> {
+        let token = format!(
+            "{}.{}.",
+            BASE64_URL_SAFE_NO_PAD.encode(json!({"alg": "RS256", "kid": "k1"}).to_string()),
+            BASE64_URL_SAFE_NO_PAD.encode(claims.to_string()),
+        );
+        let parser = JwtParser::<FirebasePayload>::parse(&token)?;
+        let options = firebase_options(PROJECT_ID, options);
+
+        id_token::validate_info(client_ids, &parser, &options)?;
+        validate_firebase_info(PROJECT_ID, &parser.payload, &options)
+    }
+
+    #[test]
+    fn valid() {
+        assert!(validate(claims(), &[PROJECT_ID], ValidationOptions::new()).is_ok());
+        assert!(validate(claims(), &[], ValidationOptions::new()).is_ok());
+    }
+
+    #[test]
+    fn subject_invalid() {
+        let mut claims = claims();
+
+        claims["sub"] = json!("");
+        assert!(matches!(validate(claims.clone(), &[PROJECT_ID], ValidationOptions::new()), Err(Error::IDTokenSubjectInvalidError(_))));
+
+        claims["sub"] = json!("u".repeat(MAX_SUBJECT_LENGTH));
+        assert!(validate(claims.clone(), &[PROJECT_ID], ValidationOptions::new()).is_ok());
+
+        claims["sub"] = json!("u".repeat(MAX_SUBJECT_LENGTH + 1));
+        assert!(matches!(validate(claims, &[PROJECT_ID], ValidationOptions::new()), Err(Error::IDTokenSubjectInvalidError(_))));
+    }
+
+    #[test]
+    fn auth_time_in_future() {
+        let mut claims = claims();
+        claims["auth_time"] = json!(now() + 5);
+
+        assert!(matches!(validate(claims.clone(), &[PROJECT_ID], ValidationOptions::new()), Err(Error::IDTokenAuthTimeInFutureError(_))));
+
+        let options = ValidationOptions::new().leeway(Duration::from_secs(30));
+        assert!(validate(claims.clone(), &[PROJECT_ID], options.clone()).is_ok());
+
+        claims["auth_time"] = json!(now() + 60);
+        assert!(matches!(validate(claims, &[PROJECT_ID], options), Err(Error::IDTokenAuthTimeInFutureError(_))));
+    }
+
+    #[test]
+    fn audience_of_other_project() {
+        let mut claims = claims();
+        claims["aud"] = json!("other-project");
+
+        assert!(matches!(validate(claims.clone(), &[PROJECT_ID], ValidationOptions::new()), Err(Error::IDTokenClientIDNotFoundError(_))));
+        // the project is checked even if the client has no client id.
+        assert!(matches!(validate(claims, &[], ValidationOptions::new()), Err(Error::IDTokenClientIDNotFoundError(_))));
+    }
+
+    #[test]
+    fn issuer_of_project_only() {
+        let options = ValidationOptions::new().issuers(["https://accounts.google.com"]);
+
+        // the issuers of the caller are replaced by the issuer of the project.
+        assert!(validate(claims(), &[PROJECT_ID], options.clone()).is_ok());
+
+        let mut claims = claims();
+        claims["iss"] = json!("https://accounts.google.com");
+        assert!(matches!(validate(claims.clone(), &[PROJECT_ID], options), Err(Error::GoogleIssuerNotMatchError(_))));
+
+        claims["iss"] = json!("https://securetoken.google.com/other-project");
+        assert!(matches!(validate(claims, &[PROJECT_ID], ValidationOptions::new()), Err(Error::GoogleIssuerNotMatchError(_))));
+    }
+
+    #[test]
+    fn future_iat_always_rejected() {
+        let mut claims = claims();
+        claims["iat"] = json!(now() + 60);
+
+        let options = ValidationOptions::new().reject_future_iat(false);
+        assert!(matches!(validate(claims, &[PROJECT_ID], options), Err(Error::IDTokenIssuedInFutureError(_))));
+    }
+}
@@ -2,76 +2,280 @@
 use web_time::{SystemTime, UNIX_EPOCH};
 #[cfg(not(feature = "wasm"))]
 use std::time::{SystemTime, UNIX_EPOCH};
-use base64::Engine;
-use base64::prelude::BASE64_URL_SAFE_NO_PAD;
-use rsa::BigUint;
-use rsa::pkcs1v15::{VerifyingKey};
-use rsa::sha2::Sha256;
-use rsa::signature::{Verifier};
-use rsa::pkcs1v15::Signature;
-
-use crate::{GOOGLE_ISS, GoogleIssuerNotMatchError, GooglePayload, HashAlgorithmUnimplementedError, IDTokenClientIDNotFoundError, MyResult};
-use crate::Cert;
+use subtle::ConstantTimeEq;
+use serde::de::DeserializeOwned;
+
+use crate::{GoogleIssuerNotMatchError, IDTokenAzpNotMatchError, IDTokenClaims, IDTokenClientIDNotFoundError, IDTokenExpiredError, IDTokenIssuedInFutureError, IDTokenNonceNotMatchError, IDTokenNotYetValidError, IDTokenTooOldError, MyResult, ValidationOptions};
 use crate::jwt_parser::JwtParser;
+use crate::validate::key::VerifyingKey;
+use crate::validate::policy;
 
-pub fn validate_info<T, V>(client_ids: T, parser: &JwtParser<GooglePayload>) -> MyResult<()>
+pub fn validate_info<T, V, P>(client_ids: T, parser: &JwtParser<P>, options: &ValidationOptions) -> MyResult<()>
     where
         T: AsRef<[V]>,
         V: AsRef<str>,
+        P: IDTokenClaims,
 {
-    if !client_ids.as_ref().is_empty() && !client_ids.as_ref().iter().any(|c| c.as_ref() == parser.payload.aud.as_str()) {
-        // bail!("id_token: audience provided does not match aud claim in the jwt");
-        Err(IDTokenClientIDNotFoundError::new(&parser.payload.aud, client_ids))?
+    let payload = &parser.payload;
+
+    let ids = client_ids.as_ref();
+
+    if !ids.is_empty() {
+        if !ids.iter().any(|c| payload.aud().contains(c.as_ref())) {
+            // bail!("id_token: audience provided does not match aud claim in the jwt");
+            Err(IDTokenClientIDNotFoundError::new(payload.aud(), ids))?
+        }
+
+        // OpenID Connect: when there are multiple audiences, `azp` must be present and be our client_id.
+        if payload.aud().is_multiple() && !payload.azp().is_some_and(|azp| ids.iter().any(|c| c.as_ref() == azp)) {
+            Err(IDTokenAzpNotMatchError::new(payload.azp(), ids))?
+        }
     }
 
-    if !GOOGLE_ISS.contains(&(parser.payload.iss.as_str())) {
-        Err(GoogleIssuerNotMatchError::new(&parser.payload.iss))?
+    if !options.issuers.iter().any(|iss| iss == payload.iss()) {
+        Err(GoogleIssuerNotMatchError::new(payload.iss(), &options.issuers))?
     }
 
     let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let leeway = options.leeway.as_secs();
 
-    if now > parser.payload.exp {
-        #[cfg(not(test))]
-        Err(crate::IDTokenExpiredError::new(now, parser.payload.exp))?
+    if now > payload.exp().saturating_add(leeway) {
+        Err(IDTokenExpiredError::new(now, payload.exp()))?
     }
 
-    Ok(())
-}
+    if options.check_nbf {
+        if let Some(nbf) = payload.nbf() {
+            if nbf > now.saturating_add(leeway) {
+                Err(IDTokenNotYetValidError::new(now, nbf))?
+            }
+        }
+    }
+
+    let iat = payload.iat();
+
+    if options.reject_future_iat && iat > now.saturating_add(leeway) {
+        Err(IDTokenIssuedInFutureError::new(now, iat))?
+    }
 
-pub fn do_validate(cert: &Cert, parser: &JwtParser<GooglePayload>) -> MyResult<()> {
-    match parser.header.alg.as_str() {
-        "RS256" => validate_rs256(
-            cert,
-            parser.msg().as_str(),
-            parser.sig.as_slice(),
-        )?,
-        a => Err(HashAlgorithmUnimplementedError::new(a))?,
-    };
+    if let Some(max_age) = options.max_age {
+        let max_age = max_age.as_secs();
+
+        if now.saturating_sub(iat) > max_age.saturating_add(leeway) {
+            Err(IDTokenTooOldError::new(now, iat, max_age))?
+        }
+    }
+
+    if let Some(expected) = &options.nonce {
+        let matched = match payload.nonce() {
+            // compare in constant time, so that the nonce cannot be guessed byte by byte.
+            Some(nonce) => bool::from(nonce.as_bytes().ct_eq(expected.as_bytes())),
+            None => false,
+        };
+
+        if !matched {
+            Err(IDTokenNonceNotMatchError::new(payload.nonce().map(|n| n.to_string())))?
+        }
+    }
+
+    policy::validate_hosted_domain(payload.hd(), options)?;
+    policy::validate_email_verified(payload.email(), payload.email_verified(), options)?;
 
     Ok(())
 }
 
-fn decode<T: AsRef<[u8]>>(b64url: T) -> MyResult<Vec<u8>> {
-    let bytes = BASE64_URL_SAFE_NO_PAD.decode(b64url)?;
-
-    Ok(bytes)
+pub fn do_validate<P: DeserializeOwned>(key: &VerifyingKey, parser: &JwtParser<P>) -> MyResult<()> {
+    key.verify(parser.msg().as_bytes(), parser.sig.as_slice())
 }
 
-pub fn validate_rs256(cert: &Cert, msg: &str, sig: &[u8]) -> MyResult<()> {
-    let dn = decode(cert.n.as_bytes())?;
-    let de = decode(cert.e.as_bytes())?;
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use base64::Engine;
+    use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+    use serde_json::{json, Value};
+    use crate::{Error, GooglePayload};
+    use super::*;
 
-    let pk = rsa::RsaPublicKey::new(
-        BigUint::from_bytes_be(dn.as_slice()),
-        BigUint::from_bytes_be(de.as_slice()),
-    )?;
+    const CLIENT_ID: &str = "client-id";
 
-    let verifying_key: VerifyingKey<Sha256> = VerifyingKey::new(pk);
+    fn now() -> u64 {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+    }
 
-    verifying_key.verify(
-        msg.as_bytes(),
-        &Signature::try_from(sig)?,
-    )?;
+    fn claims() -> Value {
+        let now = now();
 
-    Ok(())
+        json!({
+            "aud": CLIENT_ID,
+            "iss": "https://accounts.google.com",
+            "sub": "110169484474386276334",
+            "iat": now,
+            "exp": now + 3600,
+        })
+    }
+
+    /// Run `validate_info` on an unsigned token with `claims`: signatures are not checked here.
+    fn validate(claims: Value, client_ids: &[&str], options: &ValidationOptions) -> MyResult<()> {
+        let token = format!(
+            "{}.{}.",
+            BASE64_URL_SAFE_NO_PAD.encode(json!({"alg": "RS256", "kid": "k1"}).to_string()),
+            BASE64_URL_SAFE_NO_PAD.encode(claims.to_string()),
+        );
+        let parser = JwtParser::<GooglePayload>::parse(&token)?;
+
+        validate_info(client_ids, &parser, options)
+    }
+
+    #[test]
+    fn nonce_matched() {
+        let mut claims = claims();
+        claims["nonce"] = json!("n-0S6_WzA2Mj");
+
+        let options = ValidationOptions::new().nonce("n-0S6_WzA2Mj");
+        assert!(validate(claims, &[CLIENT_ID], &options).is_ok());
+    }
+
+    #[test]
+    fn nonce_mismatched() {
+        let mut claims = claims();
+        claims["nonce"] = json!("n-0S6_WzA2Mk");
+
+        let options = ValidationOptions::new().nonce("n-0S6_WzA2Mj");
+        assert!(matches!(validate(claims, &[CLIENT_ID], &options), Err(Error::IDTokenNonceNotMatchError(_))));
+    }
+
+    #[test]
+    fn nonce_missing() {
+        let options = ValidationOptions::new().nonce("n-0S6_WzA2Mj");
+        assert!(matches!(validate(claims(), &[CLIENT_ID], &options), Err(Error::IDTokenNonceNotMatchError(_))));
+    }
+
+    #[test]
+    fn nonce_not_expected() {
+        let mut claims = claims();
+        claims["nonce"] = json!("n-0S6_WzA2Mj");
+
+        assert!(validate(claims, &[CLIENT_ID], &ValidationOptions::new()).is_ok());
+    }
+
+    #[test]
+    fn audience_not_matched() {
+        let options = ValidationOptions::new();
+
+        assert!(validate(claims(), &["other", CLIENT_ID], &options).is_ok());
+        assert!(matches!(validate(claims(), &["other"], &options), Err(Error::IDTokenClientIDNotFoundError(_))));
+
+        // no client id means any audience.
+        assert!(validate(claims(), &[], &options).is_ok());
+    }
+
+    #[test]
+    fn azp_of_multiple_audiences() {
+        let options = ValidationOptions::new();
+
+        let mut claims = claims();
+        claims["aud"] = json!([CLIENT_ID, "other"]);
+        assert!(matches!(validate(claims.clone(), &[CLIENT_ID], &options), Err(Error::IDTokenAzpNotMatchError(_))));
+
+        claims["azp"] = json!("other");
+        assert!(matches!(validate(claims.clone(), &[CLIENT_ID], &options), Err(Error::IDTokenAzpNotMatchError(_))));
+
+        claims["azp"] = json!(CLIENT_ID);
+        assert!(validate(claims, &[CLIENT_ID], &options).is_ok());
+    }
+
+    #[test]
+    fn azp_of_single_audience() {
+        // `azp` is not required with a single audience, and may be another client, e.g. of an Android app.
+        let mut claims = claims();
+        claims["aud"] = json!([CLIENT_ID]);
+        claims["azp"] = json!("android-client-id");
+
+        assert!(validate(claims, &[CLIENT_ID], &ValidationOptions::new()).is_ok());
+    }
+
+    #[test]
+    fn service_accounts_not_checked() {
+        // the allow-list is only applied to service `id_token`s, so user tokens are still accepted here.
+        let mut claims = claims();
+        claims["email"] = json!("user@example.com");
+        claims["email_verified"] = json!(true);
+
+        let options = ValidationOptions::new().service_accounts(["caller@my-project.iam.gserviceaccount.com"]);
+        assert!(validate(claims, &[CLIENT_ID], &options).is_ok());
+    }
+
+    #[test]
+    fn exp_boundary() {
+        let mut claims = claims();
+        let options = ValidationOptions::new();
+
+        claims["exp"] = json!(now() + 5);
+        assert!(validate(claims.clone(), &[CLIENT_ID], &options).is_ok());
+
+        claims["exp"] = json!(now() - 5);
+        assert!(matches!(validate(claims.clone(), &[CLIENT_ID], &options), Err(Error::IDTokenExpiredError(_))));
+
+        // expired tokens are still accepted within the leeway.
+        let options = ValidationOptions::new().leeway(Duration::from_secs(30));
+        assert!(validate(claims.clone(), &[CLIENT_ID], &options).is_ok());
+
+        claims["exp"] = json!(now() - 60);
+        assert!(matches!(validate(claims, &[CLIENT_ID], &options), Err(Error::IDTokenExpiredError(_))));
+    }
+
+    #[test]
+    fn nbf_boundary() {
+        let mut claims = claims();
+        let options = ValidationOptions::new();
+
+        claims["nbf"] = json!(now() - 5);
+        assert!(validate(claims.clone(), &[CLIENT_ID], &options).is_ok());
+
+        claims["nbf"] = json!(now() + 5);
+        assert!(matches!(validate(claims.clone(), &[CLIENT_ID], &options), Err(Error::IDTokenNotYetValidError(_))));
+        assert!(validate(claims.clone(), &[CLIENT_ID], &ValidationOptions::new().check_nbf(false)).is_ok());
+
+        let options = ValidationOptions::new().leeway(Duration::from_secs(30));
+        assert!(validate(claims.clone(), &[CLIENT_ID], &options).is_ok());
+
+        claims["nbf"] = json!(now() + 60);
+        assert!(matches!(validate(claims, &[CLIENT_ID], &options), Err(Error::IDTokenNotYetValidError(_))));
+    }
+
+    #[test]
+    fn iat_in_future() {
+        let mut claims = claims();
+        claims["iat"] = json!(now() + 5);
+
+        // `iat` is not checked by default.
+        assert!(validate(claims.clone(), &[CLIENT_ID], &ValidationOptions::new()).is_ok());
+
+        let options = ValidationOptions::new().reject_future_iat(true);
+        assert!(matches!(validate(claims.clone(), &[CLIENT_ID], &options), Err(Error::IDTokenIssuedInFutureError(_))));
+
+        let options = options.leeway(Duration::from_secs(30));
+        assert!(validate(claims.clone(), &[CLIENT_ID], &options).is_ok());
+
+        claims["iat"] = json!(now() + 60);
+        assert!(matches!(validate(claims, &[CLIENT_ID], &options), Err(Error::IDTokenIssuedInFutureError(_))));
+    }
+
+    #[test]
+    fn max_age_boundary() {
+        let mut claims = claims();
+        let options = ValidationOptions::new().max_age(Duration::from_secs(600));
+
+        claims["iat"] = json!(now() - 590);
+        assert!(validate(claims.clone(), &[CLIENT_ID], &options).is_ok());
+
+        claims["iat"] = json!(now() - 610);
+        assert!(matches!(validate(claims.clone(), &[CLIENT_ID], &options), Err(Error::IDTokenTooOldError(_))));
+
+        let options = options.leeway(Duration::from_secs(30));
+        assert!(validate(claims.clone(), &[CLIENT_ID], &options).is_ok());
+
+        claims["iat"] = json!(now() - 660);
+        assert!(matches!(validate(claims, &[CLIENT_ID], &options), Err(Error::IDTokenTooOldError(_))));
+    }
 }
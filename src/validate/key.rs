@@ -0,0 +1,251 @@
+use base64::Engine;
+use base64::prelude::BASE64_URL_SAFE_NO_PAD;
+use rsa::{BigUint, RsaPublicKey};
+use rsa::{pkcs1v15, pss};
+use rsa::sha2::{Digest, Sha256, Sha384, Sha512};
+use rsa::signature::{Verifier};
+use p256::EncodedPoint;
+
+use crate::{Algorithm, Cert, CertInvalidError, Error, MyResult};
+
+/// A public key decoded from a [Cert], ready to verify signatures of one [Algorithm].
+///
+/// Decoding a key is much more expensive than verifying a signature,
+/// so [crate::Certs] keeps the decoded keys until the next refresh.
+#[derive(Debug, Clone)]
+#[allow(clippy::upper_case_acronyms)]
+pub(crate) enum VerifyingKey {
+    RS256(pkcs1v15::VerifyingKey<Sha256>),
+    RS384(pkcs1v15::VerifyingKey<Sha384>),
+    RS512(pkcs1v15::VerifyingKey<Sha512>),
+    PS256(pss::VerifyingKey<Sha256>),
+    PS384(pss::VerifyingKey<Sha384>),
+    PS512(pss::VerifyingKey<Sha512>),
+    ES256(p256::ecdsa::VerifyingKey),
+}
+
+impl VerifyingKey {
+    pub fn from_cert(cert: &Cert, alg: Algorithm) -> MyResult<Self> {
+        let key = match alg {
+            Algorithm::RS256 => Self::RS256(pkcs1v15::VerifyingKey::new(rsa_public_key(cert)?)),
+            Algorithm::RS384 => Self::RS384(pkcs1v15::VerifyingKey::new(rsa_public_key(cert)?)),
+            Algorithm::RS512 => Self::RS512(pkcs1v15::VerifyingKey::new(rsa_public_key(cert)?)),
+            Algorithm::PS256 => Self::PS256(pss_verifying_key(rsa_public_key(cert)?)),
+            Algorithm::PS384 => Self::PS384(pss_verifying_key(rsa_public_key(cert)?)),
+            Algorithm::PS512 => Self::PS512(pss_verifying_key(rsa_public_key(cert)?)),
+            Algorithm::ES256 => Self::ES256(p256_verifying_key(cert)?),
+        };
+
+        Ok(key)
+    }
+
+    pub fn verify(&self, msg: &[u8], sig: &[u8]) -> MyResult<()> {
+        match self {
+            Self::RS256(key) => key.verify(msg, &pkcs1v15::Signature::try_from(sig)?)?,
+            Self::RS384(key) => key.verify(msg, &pkcs1v15::Signature::try_from(sig)?)?,
+            Self::RS512(key) => key.verify(msg, &pkcs1v15::Signature::try_from(sig)?)?,
+            Self::PS256(key) => key.verify(msg, &pss::Signature::try_from(sig)?)?,
+            Self::PS384(key) => key.verify(msg, &pss::Signature::try_from(sig)?)?,
+            Self::PS512(key) => key.verify(msg, &pss::Signature::try_from(sig)?)?,
+            Self::ES256(key) => {
+                // JWS uses the raw `r || s` form of the signature, not DER.
+                let signature = p256::ecdsa::Signature::from_slice(sig)
+                    .map_err(Error::ES256SignatureError)?;
+
+                key
+                    .verify(msg, &signature)
+                    .map_err(Error::ES256SignatureError)?
+            }
+        };
+
+        Ok(())
+    }
+}
+
+fn decode<T: AsRef<[u8]>>(b64url: T) -> MyResult<Vec<u8>> {
+    let bytes = BASE64_URL_SAFE_NO_PAD.decode(b64url)?;
+
+    Ok(bytes)
+}
+
+fn rsa_public_key(cert: &Cert) -> MyResult<RsaPublicKey> {
+    let dn = decode(cert.n.as_bytes())?;
+    let de = decode(cert.e.as_bytes())?;
+
+    let pk = RsaPublicKey::new(
+        BigUint::from_bytes_be(dn.as_slice()),
+        BigUint::from_bytes_be(de.as_slice()),
+    )?;
+
+    Ok(pk)
+}
+
+fn pss_verifying_key<D>(pk: RsaPublicKey) -> pss::VerifyingKey<D>
+    where D: Digest
+{
+    // JWA requires the salt length to be the same as the hash output length.
+    pss::VerifyingKey::new_with_salt_len(pk, <D as Digest>::output_size())
+}
+
+fn p256_verifying_key(cert: &Cert) -> MyResult<p256::ecdsa::VerifyingKey> {
+    let (x, y) = match (&cert.x, &cert.y) {
+        (Some(x), Some(y)) => (decode(x.as_bytes())?, decode(y.as_bytes())?),
+        _ => Err(CertInvalidError::new(&cert.kid, "missing `x` or `y` for EC key"))?,
+    };
+
+    // P-256 coordinates are always 32 bytes long.
+    if x.len() != 32 || y.len() != 32 {
+        Err(CertInvalidError::new(&cert.kid, "invalid P-256 coordinates length"))?
+    }
+
+    let point = EncodedPoint::from_affine_coordinates(
+        x.as_slice().into(),
+        y.as_slice().into(),
+        false,
+    );
+
+    let verifying_key = p256::ecdsa::VerifyingKey::from_encoded_point(&point)
+        .map_err(Error::ES256SignatureError)?;
+
+    Ok(verifying_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::OnceLock;
+    use p256::ecdsa::SigningKey;
+    use rsa::RsaPrivateKey;
+    use rsa::rand_core::OsRng;
+    use rsa::signature::{RandomizedSigner, SignatureEncoding, Signer};
+    use rsa::traits::PublicKeyParts;
+    use super::*;
+
+    const MSG: &[u8] = b"header.payload";
+
+    fn rsa_key() -> &'static RsaPrivateKey {
+        static KEY: OnceLock<RsaPrivateKey> = OnceLock::new();
+
+        KEY.get_or_init(|| RsaPrivateKey::new(&mut OsRng, 2048).unwrap())
+    }
+
+    fn ec_key() -> &'static SigningKey {
+        static KEY: OnceLock<SigningKey> = OnceLock::new();
+
+        KEY.get_or_init(|| SigningKey::random(&mut OsRng))
+    }
+
+    fn cert(alg: Algorithm) -> Cert {
+        let mut cert = Cert {
+            kid: "test".to_string(),
+            alg: alg.as_str().to_string(),
+            kty: alg.key_type().to_string(),
+            e: String::new(),
+            n: String::new(),
+            crv: None,
+            x: None,
+            y: None,
+            not_after: None,
+        };
+
+        match alg {
+            Algorithm::ES256 => {
+                let point = ec_key().verifying_key().to_encoded_point(false);
+
+                cert.crv = Some("P-256".to_string());
+                cert.x = point.x().map(|x| BASE64_URL_SAFE_NO_PAD.encode(x));
+                cert.y = point.y().map(|y| BASE64_URL_SAFE_NO_PAD.encode(y));
+            }
+            _ => {
+                let pk = rsa_key().to_public_key();
+
+                cert.e = BASE64_URL_SAFE_NO_PAD.encode(pk.e().to_bytes_be());
+                cert.n = BASE64_URL_SAFE_NO_PAD.encode(pk.n().to_bytes_be());
+            }
+        }
+
+        cert
+    }
+
+    fn pss_sign<D>(msg: &[u8]) -> Vec<u8>
+        where D: Digest + rsa::sha2::digest::FixedOutputReset
+    {
+        pss::SigningKey::<D>::new_with_salt_len(rsa_key().clone(), <D as Digest>::output_size())
+            .sign_with_rng(&mut OsRng, msg)
+            .to_vec()
+    }
+
+    fn sign(alg: Algorithm, msg: &[u8]) -> Vec<u8> {
+        match alg {
+            Algorithm::RS256 => pkcs1v15::SigningKey::<Sha256>::new(rsa_key().clone()).sign(msg).to_vec(),
+            Algorithm::RS384 => pkcs1v15::SigningKey::<Sha384>::new(rsa_key().clone()).sign(msg).to_vec(),
+            Algorithm::RS512 => pkcs1v15::SigningKey::<Sha512>::new(rsa_key().clone()).sign(msg).to_vec(),
+            Algorithm::PS256 => pss_sign::<Sha256>(msg),
+            Algorithm::PS384 => pss_sign::<Sha384>(msg),
+            Algorithm::PS512 => pss_sign::<Sha512>(msg),
+            Algorithm::ES256 => {
+                let sig: p256::ecdsa::Signature = ec_key().sign(msg);
+                sig.to_vec()
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for alg in Algorithm::ALL {
+            let key = VerifyingKey::from_cert(&cert(alg), alg).unwrap();
+            let sig = sign(alg, MSG);
+
+            assert!(key.verify(MSG, &sig).is_ok(), "{}", alg);
+            assert!(key.verify(b"header.tampered", &sig).is_err(), "{}", alg);
+        }
+    }
+
+    #[test]
+    fn signature_of_other_algorithm() {
+        for signed in Algorithm::ALL {
+            let sig = sign(signed, MSG);
+
+            for alg in Algorithm::ALL.into_iter().filter(|alg| *alg != signed) {
+                // RSA certs can be decoded for every RSA algorithm, but must not accept signatures of the others.
+                if let Ok(key) = VerifyingKey::from_cert(&cert(signed), alg) {
+                    assert!(key.verify(MSG, &sig).is_err(), "{} signature verified as {}", signed, alg);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cert_alg_restricts_algorithm() {
+        let rs256 = cert(Algorithm::RS256);
+
+        assert!(rs256.is_usable_for(Algorithm::RS256));
+        assert!(!rs256.is_usable_for(Algorithm::PS256));
+        assert!(!rs256.is_usable_for(Algorithm::ES256));
+        assert!(!cert(Algorithm::ES256).is_usable_for(Algorithm::RS256));
+
+        let any = Cert { alg: String::new(), ..rs256 };
+
+        for alg in Algorithm::ALL {
+            assert_eq!(any.is_usable_for(alg), alg != Algorithm::ES256, "{}", alg);
+        }
+    }
+
+    #[test]
+    fn es256_rejects_der_signature() {
+        let key = VerifyingKey::from_cert(&cert(Algorithm::ES256), Algorithm::ES256).unwrap();
+        let sig: p256::ecdsa::Signature = ec_key().sign(MSG);
+
+        assert!(key.verify(MSG, sig.to_der().as_bytes()).is_err());
+    }
+
+    #[test]
+    fn invalid_ec_coordinates() {
+        let mut missing = cert(Algorithm::ES256);
+        missing.y = None;
+        assert!(VerifyingKey::from_cert(&missing, Algorithm::ES256).is_err());
+
+        let mut short = cert(Algorithm::ES256);
+        short.x = Some(BASE64_URL_SAFE_NO_PAD.encode([1u8; 31]));
+        assert!(VerifyingKey::from_cert(&short, Algorithm::ES256).is_err());
+    }
+}
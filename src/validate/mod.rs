@@ -1 +1,5 @@
 pub mod id_token;
+pub mod key;
+pub mod policy;
+#[cfg(not(feature = "wasm"))]
+pub mod firebase;
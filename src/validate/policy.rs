@@ -0,0 +1,140 @@
+use crate::{EmailNotVerifiedError, HostedDomainNotAllowedError, MyResult, ValidationOptions};
+
+/// Check the hosted domain (`hd`) of an account against [ValidationOptions::hosted_domains]
+/// and [ValidationOptions::reject_consumer_accounts].
+pub fn validate_hosted_domain(hd: Option<&str>, options: &ValidationOptions) -> MyResult<()> {
+    match hd {
+        Some(hd) => {
+            if !options.hosted_domains.is_empty() && !options.hosted_domains.iter().any(|d| d.eq_ignore_ascii_case(hd)) {
+                Err(HostedDomainNotAllowedError::new(Some(hd), &options.hosted_domains))?
+            }
+        }
+        None => {
+            if options.reject_consumer_accounts || !options.hosted_domains.is_empty() {
+                Err(HostedDomainNotAllowedError::new(None, &options.hosted_domains))?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Check `email` and `email_verified` against [ValidationOptions::require_verified_email].
+pub fn validate_email_verified(email: Option<&str>, email_verified: Option<bool>, options: &ValidationOptions) -> MyResult<()> {
+    if options.require_verified_email && (email.is_none() || email_verified != Some(true)) {
+        Err(EmailNotVerifiedError::new(email))?
+    }
+
+    Ok(())
+}
+
+/// Check `email` and `email_verified` against [ValidationOptions::service_accounts].
+/// Every token is rejected if no service account is allowed.
+#[cfg(not(feature = "wasm"))]
+pub fn validate_service_account(email: Option<&str>, email_verified: Option<bool>, options: &ValidationOptions) -> MyResult<()> {
+    let email = match email {
+        Some(email) if email_verified == Some(true) => email,
+        _ => Err(EmailNotVerifiedError::new(email))?,
+    };
+
+    if !options.service_accounts.iter().any(|e| e.eq_ignore_ascii_case(email)) {
+        Err(crate::ServiceAccountNotAllowedError::new(email, &options.service_accounts))?
+    }
+
+    Ok(())
+}
+
+/// Check that `expected` is one of the audiences. Unlike the check of client ids, it is never skipped, even if `expected` is empty.
+#[cfg(not(feature = "wasm"))]
+pub fn validate_audience(aud: &crate::Audience, expected: &str) -> MyResult<()> {
+    if !aud.contains(expected) {
+        Err(crate::IDTokenClientIDNotFoundError::new(aud, [expected]))?
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Error;
+    use super::*;
+
+    #[test]
+    fn hosted_domain_allowed() {
+        let options = ValidationOptions::new().hosted_domains(["example.com", "example.org"]);
+
+        assert!(validate_hosted_domain(Some("example.com"), &options).is_ok());
+        assert!(validate_hosted_domain(Some("Example.ORG"), &options).is_ok());
+        assert!(matches!(validate_hosted_domain(Some("example.net"), &options), Err(Error::HostedDomainNotAllowedError(_))));
+    }
+
+    #[test]
+    fn hosted_domain_consumer_account() {
+        // consumer (e.g. gmail.com) accounts have no `hd`.
+        assert!(validate_hosted_domain(None, &ValidationOptions::new()).is_ok());
+
+        let options = ValidationOptions::new().reject_consumer_accounts(true);
+        assert!(validate_hosted_domain(Some("example.com"), &options).is_ok());
+        assert!(matches!(validate_hosted_domain(None, &options), Err(Error::HostedDomainNotAllowedError(_))));
+
+        let options = ValidationOptions::new().hosted_domains(["example.com"]);
+        assert!(matches!(validate_hosted_domain(None, &options), Err(Error::HostedDomainNotAllowedError(_))));
+    }
+
+    #[test]
+    fn email_verified() {
+        let options = ValidationOptions::new().require_verified_email(true);
+
+        assert!(validate_email_verified(Some("user@example.com"), Some(true), &options).is_ok());
+        assert!(matches!(validate_email_verified(Some("user@example.com"), Some(false), &options), Err(Error::EmailNotVerifiedError(_))));
+        assert!(matches!(validate_email_verified(Some("user@example.com"), None, &options), Err(Error::EmailNotVerifiedError(_))));
+        assert!(matches!(validate_email_verified(None, Some(true), &options), Err(Error::EmailNotVerifiedError(_))));
+    }
+
+    #[test]
+    fn email_verified_not_required() {
+        let options = ValidationOptions::new();
+
+        assert!(validate_email_verified(Some("user@example.com"), Some(false), &options).is_ok());
+        assert!(validate_email_verified(None, None, &options).is_ok());
+    }
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn service_account_allowed() {
+        let options = ValidationOptions::new().service_accounts(["caller@my-project.iam.gserviceaccount.com"]);
+
+        assert!(validate_service_account(Some("caller@my-project.iam.gserviceaccount.com"), Some(true), &options).is_ok());
+        assert!(validate_service_account(Some("Caller@My-Project.iam.gserviceaccount.com"), Some(true), &options).is_ok());
+        assert!(matches!(
+            validate_service_account(Some("other@my-project.iam.gserviceaccount.com"), Some(true), &options),
+            Err(Error::ServiceAccountNotAllowedError(_)),
+        ));
+    }
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn service_account_not_verified() {
+        let options = ValidationOptions::new().service_accounts(["caller@my-project.iam.gserviceaccount.com"]);
+
+        assert!(matches!(
+            validate_service_account(Some("caller@my-project.iam.gserviceaccount.com"), Some(false), &options),
+            Err(Error::EmailNotVerifiedError(_)),
+        ));
+        assert!(matches!(
+            validate_service_account(Some("caller@my-project.iam.gserviceaccount.com"), None, &options),
+            Err(Error::EmailNotVerifiedError(_)),
+        ));
+        assert!(matches!(validate_service_account(None, Some(true), &options), Err(Error::EmailNotVerifiedError(_))));
+    }
+
+    #[test]
+    #[cfg(not(feature = "wasm"))]
+    fn service_account_empty_list() {
+        // any Google account can get a token for any audience, so nothing is allowed by default.
+        assert!(matches!(
+            validate_service_account(Some("caller@my-project.iam.gserviceaccount.com"), Some(true), &ValidationOptions::new()),
+            Err(Error::ServiceAccountNotAllowedError(_)),
+        ));
+    }
+}
@@ -6,11 +6,12 @@ use web_time::Instant;
 use std::time::Duration;
 use async_lock::RwLock;
 use wasm_bindgen::prelude::*;
-use crate::{Cert, Certs, GOOGLE_OAUTH_V3_USER_INFO_API, GOOGLE_SA_CERTS_URL, GoogleAccessTokenPayload, GooglePayload, utils};
+use crate::{CacheOptions, Certs, GOOGLE_OAUTH_V3_USER_INFO_API, GOOGLE_SA_CERTS_URL, GoogleAccessTokenPayload, GooglePayload, IDTokenClaims, utils, ValidationOptions};
 use anyhow::bail;
 use lazy_static::lazy_static;
 use crate::jwt_parser::JwtParser;
 use crate::validate::id_token;
+use crate::validate::key::VerifyingKey;
 
 lazy_static! {
     static ref ca: reqwest::Client = reqwest::Client::new();
@@ -20,6 +21,10 @@ lazy_static! {
 #[wasm_bindgen(getter_with_clone)]
 pub struct Client {
     client_id: String,
+    certs_url: String,
+    userinfo_url: String,
+    options: ValidationOptions,
+    cache_options: CacheOptions,
     cached_certs: Arc<RwLock<Certs>>
 }
 
@@ -29,39 +34,39 @@ impl Client {
     pub fn new(client_id: String) -> Client {
         Client {
             client_id,
+            certs_url: GOOGLE_SA_CERTS_URL.to_string(),
+            userinfo_url: GOOGLE_OAUTH_V3_USER_INFO_API.to_string(),
+            options: ValidationOptions::default(),
+            cache_options: CacheOptions::default(),
             cached_certs: Arc::default(),
         }
     }
 
+    /// Set the url to fetch certs (JWKS, or X.509 certificates in PEM) from. Cert sources are not supported in wasm, certs are always fetched from this url.
     #[wasm_bindgen]
-    pub async fn validate_id_token(&self, token: String) -> Result<GooglePayload, String> {
-        let parser: JwtParser<GooglePayload> = match JwtParser::parse(&token) {
-            Ok(jwt) => jwt,
-            Err(e) => return Err(format!("{:?}", e)),
-        };
-
-        if let Err(e) = id_token::validate_info(&[&self.client_id], &parser) {
-            return Err(format!("{:?}", e));
-        }
-
-        let cert = match self.get_cert(parser.header.alg.as_str(), parser.header.kid.as_str()).await {
-            Ok(cert) => cert,
-            Err(e) => return Err(format!("{:?}", e))
-        };
+    pub fn certs_url(mut self, url: String) -> Client {
+        self.certs_url = url;
+        self
+    }
 
-        if let Err(e) = id_token::do_validate(&cert, &parser) {
-            return Err(format!("{:?}", e));
-        }
+    /// Set the url of userinfo api, which is used in validating `access_token`.
+    #[wasm_bindgen]
+    pub fn userinfo_url(mut self, url: String) -> Client {
+        self.userinfo_url = url;
+        self
+    }
 
-        Ok(parser.payload)
+    #[wasm_bindgen]
+    pub async fn validate_id_token(&self, token: String) -> Result<GooglePayload, String> {
+        self.validate_id_token_as(token).await
     }
 
-    async fn get_cert(&self, alg: &str, kid: &str) -> anyhow::Result<Cert> {
+    async fn get_key(&self, alg: &str, kid: &str) -> anyhow::Result<Arc<VerifyingKey>> {
         {
             let cached_certs = self.cached_certs.read().await;
             if !cached_certs.need_refresh() {
-                return match cached_certs.find_cert(alg, kid) {
-                    Ok(cert) => Ok(cert),
+                return match cached_certs.find_key(alg, kid) {
+                    Ok(key) => Ok(key),
                     Err(e) => bail!("{}", e),
                 };
             }
@@ -69,20 +74,23 @@ impl Client {
 
         let mut cached_certs = self.cached_certs.write().await;
 
-        let resp = ca.get(GOOGLE_SA_CERTS_URL)
+        let resp = ca.get(&self.certs_url)
             .send()
             .await?;
 
-        let max_age = utils::parse_max_age_from_async_resp(&resp);
+        let max_age = utils::parse_ttl_from_headers(resp.headers());
         let text = resp.text().await?;
 
         *cached_certs = serde_json::from_str(&text)?;
         cached_certs.set_cache_until(
             Instant::now().add(Duration::from_secs(max_age))
         );
+        self.cache_options.apply(&mut cached_certs);
+
+        cached_certs.prepare_keys();
 
-        match cached_certs.find_cert(alg, kid) {
-            Ok(cert) => Ok(cert),
+        match cached_certs.find_key(alg, kid) {
+            Ok(key) => Ok(key),
             Err(e) => bail!("{}", e),
         }
     }
@@ -96,7 +104,7 @@ impl Client {
     }
 
     async fn do_validate_access_token(&self, token: &str) -> anyhow::Result<GoogleAccessTokenPayload> {
-        let url = format!("{}?access_token={}", GOOGLE_OAUTH_V3_USER_INFO_API, token);
+        let url = format!("{}?access_token={}", self.userinfo_url, token);
 
         let info = ca.get(url)
             .send()
@@ -109,3 +117,45 @@ impl Client {
         Ok(payload)
     }
 }
+
+impl Client {
+    /// Set the [ValidationOptions] used by `validate_id_token`.
+    /// This function is not exported to javascript, so javascript always uses the default options.
+    pub fn validation_options(mut self, options: ValidationOptions) -> Client {
+        self.options = options;
+        self
+    }
+
+    /// Set the [CacheOptions], which controls how long fetched certs are cached.
+    /// This function is not exported to javascript, so javascript always uses the default options.
+    pub fn cache_options(mut self, options: CacheOptions) -> Client {
+        self.cache_options = options;
+        self
+    }
+
+    /// Do verification with `id_token`, and deserialize the payload as `T`.
+    ///
+    /// It is useful when you need extra (or custom) claims, which are not listed in [GooglePayload].
+    /// This function is not exported to javascript, since `T` is generic.
+    pub async fn validate_id_token_as<T: IDTokenClaims>(&self, token: String) -> Result<T, String> {
+        let parser: JwtParser<T> = match JwtParser::parse(&token) {
+            Ok(jwt) => jwt,
+            Err(e) => return Err(format!("{:?}", e)),
+        };
+
+        if let Err(e) = id_token::validate_info([&self.client_id], &parser, &self.options) {
+            return Err(format!("{:?}", e));
+        }
+
+        let key = match self.get_key(parser.header.alg.as_str(), parser.header.kid.as_str()).await {
+            Ok(key) => key,
+            Err(e) => return Err(format!("{:?}", e))
+        };
+
+        if let Err(e) = id_token::do_validate(&key, &parser) {
+            return Err(format!("{:?}", e));
+        }
+
+        Ok(parser.payload)
+    }
+}